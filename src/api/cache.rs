@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::config::CacheConfig;
+use crate::errors::Result;
+
+// Bump when the entry format or the key changes meaning
+const CACHE_VERSION: &str = "1";
+
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    dir: PathBuf,
+    ttl: Duration,
+    max_bytes: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    created_at: u64,
+    response: String,
+}
+
+impl ResponseCache {
+    /// `None` when caching is off or there's no cache directory
+    pub fn from_config(config: &CacheConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+
+        Some(Self::in_dir(cache_dir()?, config))
+    }
+
+    /// A cache in `dir` whatever `enabled` says
+    pub fn in_dir(dir: PathBuf, config: &CacheConfig) -> Self {
+        // Huge values from the config mean "never", not an overflow
+        Self {
+            dir,
+            ttl: Duration::from_secs(config.ttl_hours.saturating_mul(60 * 60)),
+            max_bytes: config.max_size_mb.saturating_mul(1024 * 1024),
+        }
+    }
+
+    /// Hash of every input that can change the response. The prompt carries
+    /// both the filtered diff and the template around it.
+    pub fn key(parts: &[&str]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(CACHE_VERSION);
+        for part in parts {
+            // Length-prefixed so ("ab", "c") and ("a", "bc") differ
+            hasher.update(part.len().to_le_bytes());
+            hasher.update(part);
+        }
+        format!("{:x}", hasher.finalize())
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        let path = self.dir.join(format!("{}.json", key));
+        let entry: Entry = serde_json::from_str(&fs::read_to_string(&path).ok()?).ok()?;
+
+        if now().saturating_sub(entry.created_at) > self.ttl.as_secs() {
+            let _ = fs::remove_file(path);
+            return None;
+        }
+        Some(entry.response)
+    }
+
+    pub fn put(&self, key: &str, response: &str) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let entry = Entry {
+            created_at: now(),
+            response: response.to_string(),
+        };
+        fs::write(self.dir.join(format!("{}.json", key)), serde_json::to_string(&entry)?)?;
+        self.evict()
+    }
+
+    /// Drops the oldest entries until the cache fits its size limit
+    fn evict(&self) -> Result<()> {
+        let mut entries: Vec<(SystemTime, u64, PathBuf)> = fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                Some((metadata.modified().ok()?, metadata.len(), entry.path()))
+            })
+            .collect();
+
+        let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+        entries.sort_by_key(|(modified, _, _)| *modified);
+
+        for (_, size, path) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            fs::remove_file(path)?;
+            total -= size;
+        }
+
+        Ok(())
+    }
+}
+
+/// `$XDG_CACHE_HOME/rusty-commit/responses` or the platform equivalent
+pub fn cache_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("rusty-commit").join("responses"))
+}
+
+/// Removes every cached response, returns how many there were
+pub fn clear_cache() -> Result<usize> {
+    let dir = match cache_dir() {
+        Some(dir) if dir.exists() => dir,
+        _ => return Ok(0),
+    };
+
+    let mut removed = 0;
+    for entry in fs::read_dir(dir)? {
+        fs::remove_file(entry?.path())?;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -1 +1,6 @@
+pub mod cache;
+pub mod models;
 pub mod openrouter;
+pub mod provider;
+pub mod summarize;
+pub mod usage;
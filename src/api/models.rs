@@ -0,0 +1,197 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::api::cache::ResponseCache;
+use crate::api::provider::{http_client, ApiSettings};
+use crate::config::Provider;
+use crate::errors::{CommitError, Result};
+
+/// The list rarely changes, no need to fetch it for every `set-model`
+const MODELS_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// One entry of the provider's `/models` list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelInfo {
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Local servers usually don't report it
+    #[serde(default)]
+    pub context_length: Option<u64>,
+    #[serde(default)]
+    pub pricing: Option<ModelPricing>,
+    #[serde(default)]
+    pub architecture: Option<ModelArchitecture>,
+    /// Request parameters the model accepts, e.g. `tools` or `response_format`
+    #[serde(default)]
+    pub supported_parameters: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelArchitecture {
+    #[serde(default)]
+    pub input_modalities: Vec<String>,
+}
+
+impl ModelInfo {
+    /// What the model can do besides plain text, as far as the provider says
+    pub fn capabilities(&self) -> Vec<&'static str> {
+        let accepts = |parameter: &str| self.supported_parameters.iter().any(|p| p == parameter);
+        let mut capabilities = Vec::new();
+        if self
+            .architecture
+            .as_ref()
+            .is_some_and(|arch| arch.input_modalities.iter().any(|m| m == "image"))
+        {
+            capabilities.push("vision");
+        }
+        if accepts("tools") {
+            capabilities.push("tools");
+        }
+        if accepts("response_format") || accepts("structured_outputs") {
+            capabilities.push("json");
+        }
+        if accepts("reasoning") {
+            capabilities.push("reasoning");
+        }
+        capabilities
+    }
+
+    /// USD per million prompt and completion tokens
+    pub fn price_per_million(&self) -> Option<(f64, f64)> {
+        let pricing = self.pricing.as_ref()?;
+        let prompt: f64 = pricing.prompt.parse().ok()?;
+        let completion: f64 = pricing.completion.parse().ok()?;
+        Some((prompt * 1_000_000.0, completion * 1_000_000.0))
+    }
+
+    pub fn is_free(&self) -> bool {
+        self.price_per_million().is_some_and(|(prompt, completion)| prompt == 0.0 && completion == 0.0)
+    }
+}
+
+/// The model list and where it came from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelCatalog {
+    /// Seconds since the epoch
+    pub fetched_at: u64,
+    pub models: Vec<ModelInfo>,
+    /// Set when the provider couldn't be reached and this is an older copy
+    #[serde(skip)]
+    pub stale: bool,
+}
+
+impl ModelCatalog {
+    pub fn get(&self, id: &str) -> Option<&ModelInfo> {
+        self.models.iter().find(|model| model.id == id)
+    }
+
+    pub fn age(&self) -> Duration {
+        Duration::from_secs(now().saturating_sub(self.fetched_at))
+    }
+}
+
+/// USD per token, as strings the way OpenRouter sends them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelPricing {
+    #[serde(default)]
+    pub prompt: String,
+    #[serde(default)]
+    pub completion: String,
+}
+
+#[derive(Deserialize)]
+struct ModelList {
+    data: Vec<ModelInfo>,
+}
+
+/// Every model the provider serves, OpenRouter and OpenAI-compatible servers alike
+pub async fn list_models(api: &ApiSettings) -> Result<Vec<ModelInfo>> {
+    let mut request = http_client(api)?.get(format!("{}/models", api.base_url));
+    if let Some(api_key) = &api.api_key {
+        request = request.header("Authorization", format!("Bearer {}", api_key));
+    }
+
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let message = response.text().await.unwrap_or_default();
+        return Err(CommitError::OpenRouterApiFail { status, message });
+    }
+    Ok(response.json::<ModelList>().await?.data)
+}
+
+/// The provider's models from a fresh cached copy, the provider itself, or
+/// when that fails (offline, say) any older cached copy
+pub async fn load_models(api: &ApiSettings, refresh: bool) -> Result<ModelCatalog> {
+    if api.provider == Provider::None {
+        return Err(CommitError::ConfigError("provider \"none\" has no models".to_string()));
+    }
+
+    let path = catalog_path(api);
+    let cached = path
+        .as_ref()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str::<ModelCatalog>(&content).ok());
+    if let Some(catalog) = &cached {
+        if !refresh && catalog.age() < MODELS_TTL {
+            return Ok(catalog.clone());
+        }
+    }
+
+    match list_models(api).await {
+        Ok(models) => {
+            let catalog = ModelCatalog { fetched_at: now(), models, stale: false };
+            if let Some(path) = &path {
+                // Only a convenience, the list itself is what matters
+                let _ = fs::create_dir_all(path.parent().unwrap_or(path))
+                    .and_then(|_| fs::write(path, serde_json::to_string(&catalog).unwrap_or_default()));
+            }
+            Ok(catalog)
+        }
+        Err(e) => match cached {
+            Some(catalog) => Ok(ModelCatalog { stale: true, ..catalog }),
+            None => Err(e),
+        },
+    }
+}
+
+/// `$XDG_CACHE_HOME/rusty-commit/models/<hash of provider and url>.json`
+fn catalog_path(api: &ApiSettings) -> Option<PathBuf> {
+    let key = ResponseCache::key(&[&api.provider.to_string(), &api.base_url]);
+    dirs::cache_dir().map(|dir| dir.join("rusty-commit").join("models").join(format!("{}.json", key)))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Makes a cheap authenticated call so a bad key shows up now rather than on the first commit
+pub async fn check_api_key(api: &ApiSettings) -> Result<()> {
+    match api.provider {
+        // The model list is public, the key endpoint isn't
+        Provider::OpenRouter => {
+            let api_key = api
+                .api_key
+                .as_deref()
+                .ok_or_else(|| CommitError::ConfigError("no API key set".to_string()))?;
+            let response = http_client(api)?
+                .get(format!("{}/key", api.base_url))
+                .header("Authorization", format!("Bearer {}", api_key))
+                .send()
+                .await?;
+            if !response.status().is_success() {
+                let status = response.status();
+                let message = response.text().await.unwrap_or_default();
+                return Err(CommitError::OpenRouterApiFail { status, message });
+            }
+            Ok(())
+        }
+        Provider::Local => list_models(api).await.map(|_| ()),
+        Provider::None => Ok(()),
+    }
+}
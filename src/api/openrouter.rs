@@ -1,9 +1,14 @@
 
 
 use git2::Repository;
-use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use crate::api::cache::ResponseCache;
+use crate::api::provider::{http_client, ApiSettings};
+use crate::api::usage::{enforce_budget, record_usage, Usage};
+use crate::config::{MessageStyle, Provider};
 use crate::errors::{CommitError, Result};
+use crate::utils::hunks::Hunk;
+use crate::utils::split::{parse_split_plan, SplitPlan};
 
 #[derive(Serialize)]
 struct ChatMessage {
@@ -17,11 +22,21 @@ struct ChatRequest {
     messages: Vec<ChatMessage>,
     max_tokens: u32,
     temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    usage: Option<UsageRequest>,
+}
+
+/// Asks OpenRouter to include the cost in the response
+#[derive(Serialize)]
+struct UsageRequest {
+    include: bool,
 }
 
 #[derive(Deserialize)]
 struct ChatResponse {
     choices: Vec<Choice>,
+    #[serde(default)]
+    usage: Option<Usage>,
 }
 
 #[derive(Deserialize)]
@@ -38,28 +53,59 @@ pub async fn generate_commit_message_openrouter(
     _repo: &Repository,
     files: &[String],
     diff_content: &str,
-    api_key: &str,
-    model: &str,
+    symbols: &str,
+    api: &ApiSettings,
 ) -> Result<String> {
-    let prompt = format_commit_prompt(files, diff_content);
-    
+    let prompt = format_commit_prompt(files, diff_content, symbols, api.message_style);
+    chat_completion(api, prompt, api.max_tokens).await
+}
+
+pub async fn generate_split_plan_openrouter(
+    hunks: &[Hunk],
+    api: &ApiSettings,
+) -> Result<SplitPlan> {
+    let prompt = format_split_prompt(hunks, api.message_style);
+    let response = chat_completion(api, prompt, 1000).await?;
+    parse_split_plan(&response)
+}
+
+pub(crate) async fn chat_completion(api: &ApiSettings, prompt: String, max_tokens: u32) -> Result<String> {
+    let cache_key = ResponseCache::key(&[
+        &api.provider.to_string(),
+        &api.base_url,
+        &api.model,
+        &api.temperature.to_string(),
+        &max_tokens.to_string(),
+        &prompt,
+    ]);
+    if let Some(response) = api.cache.as_ref().and_then(|cache| cache.get(&cache_key)) {
+        api.tally.add_cache_hit();
+        return Ok(response);
+    }
+
+    enforce_budget(api)?;
+    let client = http_client(api)?;
     let request = ChatRequest {
-        model: model.to_string(),
+        model: api.model.clone(),
         messages: vec![ChatMessage {
             role: "user".to_string(),
             content: prompt,
         }],
-        max_tokens: 150,
-        temperature: 0.7,
+        max_tokens,
+        temperature: api.temperature,
+        usage: (api.provider == Provider::OpenRouter).then_some(UsageRequest { include: true }),
     };
 
-    let response = Client::new()
-        .post("https://openrouter.ai/api/v1/chat/completions")
-        .header("Authorization", format!("Bearer {}", api_key))
+    let mut request_builder = client
+        .post(format!("{}/chat/completions", api.base_url))
         .header("Content-Type", "application/json")
-        .json(&request)
-        .send()
-        .await?;
+        .json(&request);
+    // Local servers usually don't want a key
+    if let Some(api_key) = &api.api_key {
+        request_builder = request_builder.header("Authorization", format!("Bearer {}", api_key));
+    }
+
+    let response = request_builder.send().await?;
 
     if !response.status().is_success() {
         let status = response.status();
@@ -68,24 +114,78 @@ pub async fn generate_commit_message_openrouter(
     }
 
     let response_data: ChatResponse = response.json().await?;
+    // Local servers don't always report usage, the request still counts
+    api.tally.add(response_data.usage.as_ref().unwrap_or(&Usage::default()));
+    if let Some(usage) = &response_data.usage {
+        // Accounting must never cost the user their message
+        let _ = record_usage(api, usage);
+    }
     
-    response_data.choices
+    let content = response_data.choices
         .first()
-        .and_then(|choice| Some(choice.message.content.trim().to_string()))
-        .ok_or(CommitError::InvalidResponse)
+        .map(|choice| choice.message.content.trim().to_string())
+        .ok_or(CommitError::InvalidResponse)?;
+
+    // A broken cache shouldn't cost a response we already paid for
+    if let Some(cache) = &api.cache {
+        let _ = cache.put(&cache_key, &content);
+    }
+
+    Ok(content)
 }
 
-fn format_commit_prompt(files: &[String], diff_content: &str) -> String {
+fn format_commit_prompt(files: &[String], diff_content: &str, symbols: &str, style: MessageStyle) -> String {
     format!(
         r#"Generate a concise, meaningful commit message for the following changes:
 
 Files changed: {}
-
+{}
 Diff:
 {}
 
-Please provide only the commit message, no explanations or quotes. Follow conventional commit format if applicable."#,
+Please provide only the commit message, no explanations or quotes. {}"#,
         files.join(", "),
-        diff_content
+        format_symbols_section(symbols),
+        diff_content,
+        style_instruction(style)
+    )
+}
+
+pub(crate) fn style_instruction(style: MessageStyle) -> &'static str {
+    match style {
+        MessageStyle::Conventional => "Follow conventional commit format if applicable.",
+        MessageStyle::Gitmoji => "Start the subject with the gitmoji that fits the change, e.g. ✨ for a feature or 🐛 for a fix, followed by a short imperative sentence.",
+        MessageStyle::Plain => "Write a short imperative subject line without any type prefix or emoji.",
+    }
+}
+
+pub(crate) fn format_symbols_section(symbols: &str) -> String {
+    if symbols.is_empty() {
+        return String::new();
+    }
+    format!("\nSymbols changed:\n{}\n", symbols)
+}
+
+fn format_split_prompt(hunks: &[Hunk], style: MessageStyle) -> String {
+    // Long hunks only need enough lines to be recognizable
+    let changes: Vec<String> = hunks
+        .iter()
+        .map(|hunk| {
+            let preview: Vec<&str> = hunk.text.lines().take(30).collect();
+            format!("[{}] +{} -{}\n{}", hunk.id, hunk.additions, hunk.deletions, preview.join("\n"))
+        })
+        .collect();
+
+    format!(
+        r#"Split the following staged changes into small, logical commits. Each change is labelled with an id in brackets.
+
+{}
+
+Respond with only JSON in this format:
+{{"commits": [{{"message": "commit message", "hunks": ["id", "id"]}}]}}
+
+Every id must appear in exactly one commit. Order the commits so each one builds on the previous. {}"#,
+        changes.join("\n\n"),
+        style_instruction(style)
     )
 }
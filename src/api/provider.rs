@@ -0,0 +1,127 @@
+use reqwest::{Client, Url};
+use std::net::IpAddr;
+use crate::api::cache::ResponseCache;
+use crate::api::usage::UsageTally;
+use crate::config::{BudgetConfig, Config, MessageStyle, Provider};
+use crate::errors::{CommitError, Result};
+use crate::utils::secrets::resolve_secret;
+
+/// Everything needed to talk to the configured model
+#[derive(Debug, Clone)]
+pub struct ApiSettings {
+    pub provider: Provider,
+    pub base_url: String,
+    pub api_key: Option<String>,
+    pub model: String,
+    pub temperature: f32,
+    /// Completion limit for the commit message itself
+    pub max_tokens: u32,
+    pub message_style: MessageStyle,
+    pub offline: bool,
+    /// `None` when responses shouldn't be cached
+    pub cache: Option<ResponseCache>,
+    pub budget: BudgetConfig,
+    /// Recorded with each request so usage can be totalled per repository
+    pub repo: Option<String>,
+    /// Usage of this run, for reporting
+    pub tally: UsageTally,
+}
+
+impl ApiSettings {
+    /// Fails when offline mode is on but the provider would send the diff
+    /// somewhere other than this machine
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let base_url = config
+            .base_url
+            .clone()
+            .or_else(|| config.provider.default_base_url().map(str::to_string))
+            .unwrap_or_default();
+
+        let settings = Self {
+            provider: config.provider,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            // Only look up keys for providers that get called
+            api_key: match config.provider {
+                Provider::None => None,
+                _ => config.api_key.as_deref().map(resolve_secret).transpose()?,
+            },
+            model: config.default_model.clone(),
+            temperature: config.temperature,
+            max_tokens: config.max_tokens,
+            message_style: config.message_style,
+            offline: config.offline,
+            cache: ResponseCache::from_config(&config.cache),
+            budget: config.budget.clone(),
+            repo: None,
+            tally: UsageTally::default(),
+        };
+        settings.check_offline()?;
+        Ok(settings)
+    }
+
+    pub fn for_repo(mut self, repo: &git2::Repository) -> Self {
+        let root = repo.workdir().unwrap_or_else(|| repo.path());
+        self.repo = Some(root.to_string_lossy().trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Whether generation needs a model at all
+    pub fn uses_model(&self) -> bool {
+        self.provider != Provider::None
+    }
+
+    /// Errors when offline mode is on and requests would leave this machine
+    pub fn check_offline(&self) -> Result<()> {
+        if !self.offline {
+            return Ok(());
+        }
+
+        match self.provider {
+            Provider::None => Ok(()),
+            Provider::OpenRouter => Err(CommitError::OfflineViolation(
+                "provider \"openrouter\" sends the diff to openrouter.ai, set provider to \"local\" or \"none\""
+                    .to_string(),
+            )),
+            Provider::Local if is_loopback(&self.base_url) => Ok(()),
+            Provider::Local => Err(CommitError::OfflineViolation(format!(
+                "base_url {} is not on this machine, only localhost addresses are allowed",
+                self.base_url
+            ))),
+        }
+    }
+}
+
+/// The one place an HTTP client gets built, so offline mode can't be bypassed
+pub(crate) fn http_client(settings: &ApiSettings) -> Result<Client> {
+    if !settings.uses_model() {
+        return Err(CommitError::OfflineViolation(
+            "provider \"none\" never makes requests".to_string(),
+        ));
+    }
+    settings.check_offline()?;
+
+    // A proxy would carry requests for this machine off it
+    if is_loopback(&settings.base_url) {
+        Ok(Client::builder().no_proxy().build()?)
+    } else {
+        Ok(Client::new())
+    }
+}
+
+/// Whether `base_url` points at this machine
+pub fn is_loopback(base_url: &str) -> bool {
+    let url = match Url::parse(base_url) {
+        Ok(url) => url,
+        Err(_) => return false,
+    };
+
+    match url.host_str() {
+        Some("localhost") => true,
+        Some(host) => host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback()),
+        None => false,
+    }
+}
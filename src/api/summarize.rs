@@ -0,0 +1,123 @@
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use crate::api::openrouter::{chat_completion, format_symbols_section, style_instruction};
+use crate::api::provider::ApiSettings;
+use crate::config::MessageStyle;
+use crate::errors::Result;
+use crate::utils::budget::{allocate, budget_diff, truncate_lines, DiffBudget};
+use crate::utils::diff::DiffSummary;
+
+// Stay polite with the API when a refactor touches hundreds of files
+const MAX_CONCURRENT_REQUESTS: usize = 4;
+
+/// Two-stage generation for diffs far beyond the model's context: summarize
+/// each part concurrently, then write the message from the summaries.
+pub async fn generate_commit_message_map_reduce(
+    files: &[String],
+    chunks: &[DiffSummary],
+    symbols: &str,
+    budget: &DiffBudget,
+    api: &ApiSettings,
+) -> Result<String> {
+    let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_REQUESTS));
+    let mut requests = JoinSet::new();
+
+    for (i, chunk) in chunks.iter().enumerate() {
+        let prompt = format_summary_prompt(&budget_diff(chunk, budget));
+        let semaphore = semaphore.clone();
+        let api = api.clone();
+
+        requests.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            (i, chat_completion(&api, prompt, 300).await)
+        });
+    }
+
+    let mut summaries = vec![String::new(); chunks.len()];
+    while let Some(joined) = requests.join_next().await {
+        let (i, summary) = joined?;
+        summaries[i] = summary?;
+    }
+
+    let prompt = format_reduce_prompt(files, chunks, &summaries, symbols, api.message_style, budget);
+    chat_completion(api, prompt, api.max_tokens).await
+}
+
+fn format_summary_prompt(diff_content: &str) -> String {
+    format!(
+        r#"The following diff is one part of a larger change. Summarize what it changes and why in a few short bullet points, naming the important functions, types and files. Do not write a commit message.
+
+Diff:
+{}"#,
+        diff_content
+    )
+}
+
+/// The final prompt, kept within the budget: when the parts don't fit, files
+/// are only counted and each summary gets a share of what's left
+pub fn format_reduce_prompt(
+    files: &[String],
+    chunks: &[DiffSummary],
+    summaries: &[String],
+    symbols: &str,
+    style: MessageStyle,
+    budget: &DiffBudget,
+) -> String {
+    let headers: Vec<String> = chunks
+        .iter()
+        .enumerate()
+        .map(|(i, chunk)| format!("Part {} ({})", i + 1, chunk.paths().join(", ")))
+        .collect();
+    let prompt = render_reduce_prompt(&files.join(", "), &headers, summaries, symbols, style);
+    if budget.estimate_tokens(&prompt) <= budget.max_tokens {
+        return prompt;
+    }
+
+    let file_list = format!("{} files", files.len());
+    let headers: Vec<String> = chunks
+        .iter()
+        .enumerate()
+        .map(|(i, chunk)| format!("Part {} ({} files)", i + 1, chunk.files.len()))
+        .collect();
+    let empty = vec![String::new(); summaries.len()];
+    let fixed = budget.estimate_tokens(&render_reduce_prompt(&file_list, &headers, &empty, symbols, style));
+
+    let costs: Vec<usize> = summaries.iter().map(|s| budget.estimate_tokens(s) + s.lines().count()).collect();
+    let shares = allocate(&vec![false; summaries.len()], &costs, budget.max_tokens.saturating_sub(fixed));
+    let shortened: Vec<String> = summaries
+        .iter()
+        .zip(shares)
+        .map(|(summary, share)| truncate_lines(summary, share, usize::MAX, budget).0)
+        .collect();
+
+    render_reduce_prompt(&file_list, &headers, &shortened, symbols, style)
+}
+
+fn render_reduce_prompt(
+    file_list: &str,
+    headers: &[String],
+    summaries: &[String],
+    symbols: &str,
+    style: MessageStyle,
+) -> String {
+    let parts: Vec<String> = headers
+        .iter()
+        .zip(summaries)
+        .map(|(header, summary)| format!("{}:\n{}", header, summary.trim_end()))
+        .collect();
+
+    format!(
+        r#"Generate a concise, meaningful commit message for the following changes. The diff is too large to show, so here are summaries of its parts:
+
+Files changed: {}
+{}
+{}
+
+Please provide only the commit message, no explanations or quotes. {}"#,
+        file_list,
+        format_symbols_section(symbols),
+        parts.join("\n\n"),
+        style_instruction(style)
+    )
+}
@@ -0,0 +1,176 @@
+use chrono::{DateTime, Datelike, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use crate::api::provider::ApiSettings;
+use crate::config::{BudgetAction, BudgetConfig};
+use crate::errors::{CommitError, Result};
+
+/// The `usage` block of a chat completion response
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Usage {
+    #[serde(default)]
+    pub prompt_tokens: u64,
+    #[serde(default)]
+    pub completion_tokens: u64,
+    /// Only OpenRouter reports it, and only when asked to
+    #[serde(default)]
+    pub cost: Option<f64>,
+}
+
+/// One line of the ledger
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub timestamp: DateTime<Utc>,
+    pub provider: String,
+    pub model: String,
+    pub repo: Option<String>,
+    #[serde(flatten)]
+    pub usage: Usage,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UsageTotals {
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost: f64,
+}
+
+/// What the requests made through one `ApiSettings` and its clones used,
+/// shared so map-reduce tasks count too
+#[derive(Debug, Clone, Default)]
+pub struct UsageTally {
+    state: Arc<Mutex<TallyState>>,
+    /// This month's cost in the ledger, read on the first budget check
+    ledger_cost: Arc<Mutex<Option<f64>>>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TallyState {
+    /// Answered by the provider, cache hits aren't counted
+    pub totals: UsageTotals,
+    pub cache_hits: u64,
+    /// Whether every request reported a cost
+    pub cost_known: bool,
+}
+
+impl UsageTally {
+    pub fn add(&self, usage: &Usage) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.cost_known = usage.cost.is_some() && (state.totals.requests == 0 || state.cost_known);
+        state.totals.requests += 1;
+        state.totals.prompt_tokens += usage.prompt_tokens;
+        state.totals.completion_tokens += usage.completion_tokens;
+        state.totals.cost += usage.cost.unwrap_or(0.0);
+    }
+
+    pub fn add_cache_hit(&self) {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).cache_hits += 1;
+    }
+
+    pub fn state(&self) -> TallyState {
+        *self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Spending this month: the ledger as it was before this run's first
+    /// request plus what the run has spent since
+    pub fn month_to_date_cost(&self) -> Result<f64> {
+        let mut ledger_cost = self.ledger_cost.lock().unwrap_or_else(|e| e.into_inner());
+        let before = match *ledger_cost {
+            Some(cost) => cost,
+            None => *ledger_cost.insert(month_to_date_cost(&read_ledger()?)),
+        };
+        Ok(before + self.state().totals.cost)
+    }
+}
+
+/// `$XDG_DATA_HOME/rusty-commit/usage.jsonl` or the platform equivalent
+pub fn ledger_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("rusty-commit").join("usage.jsonl"))
+}
+
+pub fn record_usage(api: &ApiSettings, usage: &Usage) -> Result<()> {
+    let path = match ledger_path() {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let record = UsageRecord {
+        timestamp: Utc::now(),
+        provider: api.provider.to_string(),
+        model: api.model.clone(),
+        repo: api.repo.clone(),
+        usage: usage.clone(),
+    };
+    let mut ledger = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(ledger, "{}", serde_json::to_string(&record)?)?;
+    Ok(())
+}
+
+/// Every recorded request, skipping lines a crash may have left half written
+pub fn read_ledger() -> Result<Vec<UsageRecord>> {
+    let path = match ledger_path() {
+        Some(path) if path.exists() => path,
+        _ => return Ok(Vec::new()),
+    };
+
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+pub fn totals_by<K: Ord>(records: &[UsageRecord], key: impl Fn(&UsageRecord) -> K) -> BTreeMap<K, UsageTotals> {
+    let mut totals: BTreeMap<K, UsageTotals> = BTreeMap::new();
+    for record in records {
+        let total = totals.entry(key(record)).or_default();
+        total.requests += 1;
+        total.prompt_tokens += record.usage.prompt_tokens;
+        total.completion_tokens += record.usage.completion_tokens;
+        total.cost += record.usage.cost.unwrap_or(0.0);
+    }
+    totals
+}
+
+/// Reported cost of the current calendar month, in local time
+pub fn month_to_date_cost(records: &[UsageRecord]) -> f64 {
+    let now = Local::now();
+    records
+        .iter()
+        .filter(|record| {
+            let local = record.timestamp.with_timezone(&Local);
+            local.year() == now.year() && local.month() == now.month()
+        })
+        .filter_map(|record| record.usage.cost)
+        .sum()
+}
+
+/// `Some((spent, limit))` once this month's spending in `records` has
+/// reached the limit
+pub fn budget_status(budget: &BudgetConfig, records: &[UsageRecord]) -> Option<(f64, f64)> {
+    let limit = budget.monthly_limit?;
+    let spent = month_to_date_cost(records);
+    (spent >= limit).then_some((spent, limit))
+}
+
+/// Refuses the request when the budget is spent and set to block
+pub(crate) fn enforce_budget(api: &ApiSettings) -> Result<()> {
+    let limit = match api.budget.monthly_limit {
+        Some(limit) if api.budget.action == BudgetAction::Block => limit,
+        _ => return Ok(()),
+    };
+
+    let spent = api.tally.month_to_date_cost()?;
+    if spent >= limit {
+        Err(CommitError::BudgetExceeded { spent, limit })
+    } else {
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(name = "rusty-commit")]
@@ -16,17 +17,53 @@ pub struct Cli {
     #[arg(short, long)]
     pub interactive: bool,
 
-    /// Force commit without confirmation
-    #[arg(short, long)]
+    /// Answer yes to every confirmation: commit without asking, accept the split plan
+    #[arg(short, long, visible_alias = "yes", short_alias = 'y')]
     pub force: bool,
 
+    /// Never prompt, fail with exit code 3 when an answer is needed. Implied when stdin isn't a terminal
+    #[arg(long)]
+    pub no_input: bool,
+
+    /// Pick hunks to stage before generate or commit, like git add -p
+    #[arg(short, long)]
+    pub patch: bool,
+
+    /// Skip the pre-commit and commit-msg hooks
+    #[arg(short, long)]
+    pub no_verify: bool,
+
+    /// Never send the diff over the network: use a local provider or the offline generator
+    #[arg(long)]
+    pub offline: bool,
+
+    /// Always ask the model, ignoring cached responses
+    #[arg(long)]
+    pub no_cache: bool,
+
     /// Enable verbose output
     #[arg(short, long)]
     pub verbose: bool,
 
-    /// AI model to use (default: gpt-3.5-turbo)
-    #[arg(long, default_value = "openai/gpt-3.5-turbo")]
-    pub model: String,
+    /// AI model to use (overrides config)
+    #[arg(long)]
+    pub model: Option<String>,
+
+    /// Sampling temperature (overrides config)
+    #[arg(long)]
+    pub temperature: Option<f32>,
+
+    /// Maximum tokens in the generated message (overrides config)
+    #[arg(long)]
+    pub max_tokens: Option<u32>,
+
+    /// Where messages come from (overrides config)
+    #[arg(long, value_parser = ["openrouter", "local", "none"])]
+    pub provider: Option<String>,
+
+    /// Maximum diff lines sent to the model (overrides config)
+    #[arg(long)]
+    pub max_diff_lines: Option<usize>,
 
     /// API key (overrides config file)
     #[arg(long)]
@@ -35,6 +72,49 @@ pub struct Cli {
     /// Config file path
     #[arg(long)]
     pub config: Option<String>,
+
+    /// Use this [profile.<name>] section instead of the one matching the branch or changes
+    #[arg(long)]
+    pub profile: Option<String>,
+
+    /// Output format for generate and commit. json prints one report on stdout and never prompts
+    #[arg(long, value_enum, default_value = "text")]
+    pub format: OutputFormat,
+}
+
+impl Cli {
+    /// The settings passed as flags, the top layer of the configuration.
+    /// Flags that weren't given leave the configured value alone.
+    pub fn config_overrides(&self) -> toml::Table {
+        let mut overrides = toml::Table::new();
+        if let Some(model) = &self.model {
+            overrides.insert("default_model".to_string(), model.clone().into());
+        }
+        if let Some(temperature) = self.temperature {
+            overrides.insert("temperature".to_string(), f64::from(temperature).into());
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            overrides.insert("max_tokens".to_string(), i64::from(max_tokens).into());
+        }
+        if let Some(provider) = &self.provider {
+            overrides.insert("provider".to_string(), provider.clone().into());
+        }
+        if let Some(max_diff_lines) = self.max_diff_lines {
+            overrides.insert("max_diff_lines".to_string(), (max_diff_lines as i64).into());
+        }
+        if let Some(api_key) = &self.api_key {
+            overrides.insert("api_key".to_string(), api_key.clone().into());
+        }
+        if self.offline {
+            overrides.insert("offline".to_string(), true.into());
+        }
+        if self.no_cache {
+            let mut cache = toml::Table::new();
+            cache.insert("enabled".to_string(), false.into());
+            overrides.insert("cache".to_string(), cache.into());
+        }
+        overrides
+    }
 }
 
 #[derive(Subcommand)]
@@ -45,24 +125,154 @@ pub enum Commands {
         message: Option<String>,
     },
     /// Generate commit message only
-    Generate,
-    /// Install as git pre-commit hook
+    Generate {
+        /// Write the message above what this file holds, as the prepare-commit-msg hook does
+        #[arg(long, value_name = "FILE")]
+        message_file: Option<PathBuf>,
+    },
+    /// Split staged changes into several logical commits
+    Split,
+    /// Install the prepare-commit-msg hook so plain `git commit` gets a generated message
     InstallHook,
+    /// Set up provider, API key, model and message style step by step
+    Init,
     /// Configure API keys and settings
     Config {
         #[command(subcommand)]
         config_command: ConfigCommands,
     },
+    /// Browse the models the provider serves
+    Models {
+        #[command(subcommand)]
+        models_command: ModelsCommands,
+    },
+    /// Show token usage and cost recorded for past requests
+    Usage {
+        /// Only show totals by this key
+        #[arg(long, value_enum)]
+        by: Option<UsageGrouping>,
+    },
+    /// Manage cached model responses
+    Cache {
+        #[command(subcommand)]
+        cache_command: CacheCommands,
+    },
 }
 
 #[derive(Subcommand)]
 pub enum ConfigCommands {
     /// Set API key
     SetApiKey,
-    /// Set default model
+    /// Set default model, checked against the provider's model list
     SetModel {
         model: String,
+        /// Set it even if the provider doesn't list it
+        #[arg(long)]
+        skip_check: bool,
+    },
+    /// Print one setting, like `git config <key>`
+    Get {
+        key: String,
+        #[command(flatten)]
+        scope: ConfigScope,
+    },
+    /// Change one setting, checked against its type and range
+    Set {
+        key: String,
+        value: String,
+        #[command(flatten)]
+        scope: ConfigScope,
+    },
+    /// Remove one setting so the next layer's value applies
+    Unset {
+        key: String,
+        #[command(flatten)]
+        scope: ConfigScope,
+    },
+    /// Open a config file in $VISUAL or $EDITOR
+    Edit {
+        #[command(flatten)]
+        scope: ConfigScope,
     },
+    /// Check every config file and override against the schema
+    Validate,
     /// Show current configuration
-    Show,
+    Show {
+        /// Also show where each value came from
+        #[arg(long)]
+        origin: bool,
+    },
+}
+
+/// Which file `config get/set/unset/edit` works on
+#[derive(Args, Clone, Copy)]
+pub struct ConfigScope {
+    /// The user-wide file, or the one given with --config (the default for changes)
+    #[arg(long, conflicts_with = "local")]
+    pub global: bool,
+    /// The repository's .rusty-commit.toml
+    #[arg(long)]
+    pub local: bool,
+}
+
+#[derive(Subcommand)]
+pub enum ModelsCommands {
+    /// List models with context length, pricing and capabilities
+    List {
+        /// Fuzzy search on the model id, e.g. "sonet" or "gpt4o"
+        search: Option<String>,
+        /// Only models with at least this many tokens of context
+        #[arg(long, value_name = "TOKENS")]
+        min_context: Option<u64>,
+        /// Only models costing at most this much per million prompt tokens, in USD
+        #[arg(long, value_name = "USD")]
+        max_price: Option<f64>,
+        /// Only free models
+        #[arg(long)]
+        free: bool,
+        /// Only models that have this capability, can be given more than once
+        #[arg(long, value_enum)]
+        capability: Vec<ModelCapability>,
+        /// Fetch the list again instead of using the cached copy
+        #[arg(long)]
+        refresh: bool,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ModelCapability {
+    Vision,
+    Tools,
+    Json,
+    Reasoning,
+}
+
+impl ModelCapability {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModelCapability::Vision => "vision",
+            ModelCapability::Tools => "tools",
+            ModelCapability::Json => "json",
+            ModelCapability::Reasoning => "reasoning",
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum CacheCommands {
+    /// Delete all cached responses
+    Clear,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum UsageGrouping {
+    Day,
+    Model,
+    Repo,
 }
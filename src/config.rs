@@ -1,89 +1,862 @@
-use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 use std::fs;
 use dirs;
 use crate::errors::{CommitError, Result};
+use crate::utils::fuzzy::edit_distance;
+use crate::utils::glob::glob_set;
+use crate::utils::secrets::SecretRef;
 
+/// Bumped when a setting changes meaning, newer files are refused
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Every file holds a partial config, missing settings come from `Default`
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Config {
+    #[serde(deserialize_with = "version")]
+    pub version: u32,
     pub api_key: Option<String>,
     pub default_model: String,
+    #[serde(deserialize_with = "positive")]
     pub max_diff_lines: usize,
+    #[serde(deserialize_with = "temperature")]
     pub temperature: f32,
+    #[serde(deserialize_with = "positive")]
     pub max_tokens: u32,
+    pub commit_backend: CommitBackend,
+    pub message_style: MessageStyle,
+    pub provider: Provider,
+    /// Chat completions endpoint, defaults to the provider's own
+    pub base_url: Option<String>,
+    /// Never send anything over the network, see `Provider`
+    pub offline: bool,
+    pub cache: CacheConfig,
+    pub budget: BudgetConfig,
+    /// `[profile.<name>]` sections, see `select_profile`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub profile: BTreeMap<String, Profile>,
+}
+
+/// Settings that apply on matching branches or changes. A profile with
+/// neither `branches` nor `paths` is only used through `--profile`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(try_from = "toml::Table")]
+pub struct Profile {
+    /// Branch globs, e.g. `release/*`
+    #[serde(default)]
+    pub branches: Vec<String>,
+    /// Globs every changed path has to match, e.g. `docs/**`
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// Any other setting, checked against `Config`
+    #[serde(flatten)]
+    pub settings: toml::Table,
+}
+
+/// Cached model responses, keyed by everything that went into the request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    pub enabled: bool,
+    pub ttl_hours: u64,
+    #[serde(deserialize_with = "positive")]
+    pub max_size_mb: u64,
+}
+
+/// Spending limit in the provider's currency (USD for OpenRouter)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BudgetConfig {
+    #[serde(deserialize_with = "monthly_limit")]
+    pub monthly_limit: Option<f64>,
+    pub action: BudgetAction,
+}
+
+// Range checks run while deserializing so errors point at the offending line
+
+impl TryFrom<toml::Table> for Profile {
+    type Error = String;
+
+    fn try_from(mut settings: toml::Table) -> std::result::Result<Self, String> {
+        let mut globs = |key: &str| match settings.remove(key) {
+            Some(value) => value.try_into::<Vec<String>>().map_err(|_| format!("{} must be a list of globs", key)),
+            None => Ok(Vec::new()),
+        };
+        let branches = globs("branches")?;
+        let paths = globs("paths")?;
+
+        if settings.contains_key("profile") {
+            return Err("profiles can't be nested".to_string());
+        }
+        toml::Value::Table(settings.clone())
+            .try_into::<Config>()
+            .map_err(|e| e.message().to_string())?;
+        Ok(Self { branches, paths, settings })
+    }
+}
+
+fn version<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<u32, D::Error> {
+    let version = u32::deserialize(deserializer)?;
+    if version == 0 || version > CONFIG_VERSION {
+        return Err(D::Error::custom(format!(
+            "unsupported config version {}, this rusty-commit reads version {}",
+            version, CONFIG_VERSION
+        )));
+    }
+    Ok(version)
+}
+
+fn positive<'de, D, T>(deserializer: D) -> std::result::Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default + PartialEq,
+{
+    let value = T::deserialize(deserializer)?;
+    if value == T::default() {
+        return Err(D::Error::custom("must be greater than 0"));
+    }
+    Ok(value)
+}
+
+fn temperature<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<f32, D::Error> {
+    let temperature = f32::deserialize(deserializer)?;
+    if !(0.0..=2.0).contains(&temperature) {
+        return Err(D::Error::custom(format!("temperature must be between 0 and 2, got {}", temperature)));
+    }
+    Ok(temperature)
+}
+
+fn monthly_limit<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Option<f64>, D::Error> {
+    let limit = f64::deserialize(deserializer)?;
+    if limit < 0.0 {
+        return Err(D::Error::custom("monthly_limit can't be negative"));
+    }
+    Ok(Some(limit))
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetAction {
+    /// Print a warning and carry on
+    #[default]
+    Warn,
+    /// Stop calling the model, messages come from the rule-based generator
+    Block,
+}
+
+impl std::fmt::Display for BudgetAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BudgetAction::Warn => write!(f, "warn"),
+            BudgetAction::Block => write!(f, "block"),
+        }
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ttl_hours: 24 * 7,
+            max_size_mb: 50,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommitBackend {
+    /// Commit in-process through libgit2
+    #[default]
+    Git2,
+    /// Shell out to `git commit`
+    Cli,
+}
+
+impl std::str::FromStr for CommitBackend {
+    type Err = CommitError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "git2" => Ok(CommitBackend::Git2),
+            "cli" => Ok(CommitBackend::Cli),
+            other => Err(CommitError::ConfigError(format!(
+                "Unknown commit_backend \"{}\", expected \"git2\" or \"cli\"",
+                other
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for CommitBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommitBackend::Git2 => write!(f, "git2"),
+            CommitBackend::Cli => write!(f, "cli"),
+        }
+    }
+}
+
+/// How generated messages are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageStyle {
+    /// `feat(parser): handle empty input`
+    #[default]
+    Conventional,
+    /// `✨ Handle empty input in the parser`
+    Gitmoji,
+    /// `Handle empty input in the parser`
+    Plain,
+}
+
+impl MessageStyle {
+    pub const ALL: [MessageStyle; 3] = [MessageStyle::Conventional, MessageStyle::Gitmoji, MessageStyle::Plain];
+}
+
+impl std::fmt::Display for MessageStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MessageStyle::Conventional => write!(f, "conventional"),
+            MessageStyle::Gitmoji => write!(f, "gitmoji"),
+            MessageStyle::Plain => write!(f, "plain"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    /// openrouter.ai, the diff leaves the machine
+    #[default]
+    OpenRouter,
+    /// An OpenAI-compatible server on this machine, e.g. Ollama or llama.cpp
+    Local,
+    /// No model at all, messages come from the rule-based generator
+    None,
+}
+
+impl Provider {
+    pub const ALL: [Provider; 3] = [Provider::OpenRouter, Provider::Local, Provider::None];
+
+    pub fn default_base_url(&self) -> Option<&'static str> {
+        match self {
+            Provider::OpenRouter => Some("https://openrouter.ai/api/v1"),
+            Provider::Local => Some("http://localhost:11434/v1"),
+            Provider::None => None,
+        }
+    }
+}
+
+impl std::str::FromStr for Provider {
+    type Err = CommitError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "openrouter" => Ok(Provider::OpenRouter),
+            "local" => Ok(Provider::Local),
+            "none" => Ok(Provider::None),
+            other => Err(CommitError::ConfigError(format!(
+                "Unknown provider \"{}\", expected \"openrouter\", \"local\" or \"none\"",
+                other
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for Provider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Provider::OpenRouter => write!(f, "openrouter"),
+            Provider::Local => write!(f, "local"),
+            Provider::None => write!(f, "none"),
+        }
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            version: CONFIG_VERSION,
             api_key: None,
             default_model: "openai/gpt-3.5-turbo".to_string(),
             max_diff_lines: 1000,
             temperature: 0.7,
             max_tokens: 150,
+            commit_backend: CommitBackend::default(),
+            message_style: MessageStyle::default(),
+            provider: Provider::default(),
+            base_url: None,
+            offline: false,
+            cache: CacheConfig::default(),
+            budget: BudgetConfig::default(),
+            profile: BTreeMap::new(),
         }
     }
 }
 
-pub fn load_config(config_path: Option<&str>) -> Result<Config> {
-    let config_file = find_config_file(config_path)?;
+pub(crate) const ENV_PREFIX: &str = "RUSTY_COMMIT_";
+const REPO_CONFIG_FILE: &str = ".rusty-commit.toml";
 
-    if config_file.exists() {
-        let content = fs::read_to_string(&config_file)?;
-        let config: toml::Value = toml::from_str(&content)?;
-        let config: Config = config.try_into()?;
-        Ok(config)
-    } else {
-        Ok(Config::default())
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    String,
+    Integer,
+    Float,
+    Bool,
+}
+
+/// Every setting by its dotted key, e.g. `cache.ttl_hours`
+pub const CONFIG_KEYS: &[(&str, ValueKind)] = &[
+    ("version", ValueKind::Integer),
+    ("api_key", ValueKind::String),
+    ("default_model", ValueKind::String),
+    ("max_diff_lines", ValueKind::Integer),
+    ("temperature", ValueKind::Float),
+    ("max_tokens", ValueKind::Integer),
+    ("commit_backend", ValueKind::String),
+    ("message_style", ValueKind::String),
+    ("provider", ValueKind::String),
+    ("base_url", ValueKind::String),
+    ("offline", ValueKind::Bool),
+    ("cache.enabled", ValueKind::Bool),
+    ("cache.ttl_hours", ValueKind::Integer),
+    ("cache.max_size_mb", ValueKind::Integer),
+    ("budget.monthly_limit", ValueKind::Float),
+    ("budget.action", ValueKind::String),
+];
+
+/// Settings that may only be references in repo config
+pub const SECRET_KEYS: &[&str] = &["api_key"];
+
+/// Where a setting's value came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigOrigin {
+    Default,
+    Global(PathBuf),
+    Repo(PathBuf),
+    /// The file passed with `--config`
+    Explicit(PathBuf),
+    /// A `[profile.<name>]` section of one of the files
+    Profile(String),
+    Env(String),
+    Cli,
+}
+
+impl std::fmt::Display for ConfigOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigOrigin::Default => write!(f, "default"),
+            ConfigOrigin::Global(path) => write!(f, "global config {}", path.display()),
+            ConfigOrigin::Repo(path) => write!(f, "repo config {}", path.display()),
+            ConfigOrigin::Explicit(path) => write!(f, "--config {}", path.display()),
+            ConfigOrigin::Profile(name) => write!(f, "profile {}", name),
+            ConfigOrigin::Env(name) => write!(f, "env {}", name),
+            ConfigOrigin::Cli => write!(f, "command line"),
+        }
     }
 }
 
-pub fn save_config(config: &Config, config_path: Option<&str>) -> Result<()> {
-    let config_file = find_config_file(config_path)?;
+/// Everything the configuration is assembled from, lowest precedence first:
+/// defaults, global file, repo file, the active profile, `RUSTY_COMMIT_*`
+/// variables, CLI flags
+#[derive(Debug, Clone, Default)]
+pub struct ConfigSources {
+    pub global_path: Option<PathBuf>,
+    pub repo_root: Option<PathBuf>,
+    /// Replaces the global and repo files when set
+    pub explicit_path: Option<PathBuf>,
+    pub env: Vec<(String, String)>,
+    pub cli: toml::Table,
+    /// `--profile`, used instead of matching one
+    pub profile: Option<String>,
+    /// Current branch, for profiles selected by `branches`
+    pub branch: Option<String>,
+    /// Paths being committed, for profiles selected by `paths`
+    pub changed_paths: Vec<String>,
+}
 
-    // Create directory if it doesn't exist
-    if let Some(parent) = config_file.parent() {
-        fs::create_dir_all(parent)?;
+impl ConfigSources {
+    pub fn discover(explicit_path: Option<&str>, repo: Option<&git2::Repository>, cli: toml::Table) -> Self {
+        Self {
+            global_path: global_config_path(),
+            repo_root: repo.and_then(|repo| repo.workdir()).map(PathBuf::from),
+            explicit_path: explicit_path.map(PathBuf::from),
+            env: std::env::vars().filter(|(name, _)| name.starts_with(ENV_PREFIX)).collect(),
+            cli,
+            profile: None,
+            branch: repo.and_then(current_branch),
+            changed_paths: Vec::new(),
+        }
     }
 
-    let content = toml::to_string_pretty(config)?;
-    fs::write(config_file, content)?;
+    /// The file `config set` and friends write to by default
+    pub fn writable_path(&self) -> Option<PathBuf> {
+        self.explicit_path.clone().or_else(|| self.global_path.clone())
+    }
+
+    pub fn repo_path(&self) -> Option<PathBuf> {
+        self.repo_root.as_ref().map(|root| root.join(REPO_CONFIG_FILE))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LoadedConfig {
+    pub config: Config,
+    /// Name of the profile whose settings were applied
+    pub profile: Option<String>,
+    /// The merged settings by key, before conversion
+    pub values: toml::Table,
+    /// Where `api_key` came from when it was left out because the repo
+    /// picked the endpoint, see `withhold_key`
+    pub withheld_key: Option<ConfigOrigin>,
+    /// Secrets in the repo config that were left out because a committed
+    /// file may not hold them, see `check_repo_value`
+    pub ignored_keys: Vec<String>,
+    origins: HashMap<String, ConfigOrigin>,
+}
+
+impl LoadedConfig {
+    pub fn origin(&self, key: &str) -> ConfigOrigin {
+        self.origins.get(key).cloned().unwrap_or(ConfigOrigin::Default)
+    }
+
+    pub fn value(&self, key: &str) -> Option<&toml::Value> {
+        get_dotted(&self.values, key)
+    }
+}
+
+pub fn load_config(sources: &ConfigSources) -> Result<LoadedConfig> {
+    let mut values = match toml::Value::try_from(Config::default())? {
+        toml::Value::Table(table) => table,
+        _ => toml::Table::new(),
+    };
+    let mut origins = HashMap::new();
+    // The same without anything the repo config says, see `withhold_key`
+    let mut own_values = values.clone();
+
+    let mut files = Vec::new();
+    match &sources.explicit_path {
+        Some(path) => files.push((path.clone(), ConfigOrigin::Explicit(path.clone()))),
+        None => {
+            if let Some(path) = &sources.global_path {
+                files.push((path.clone(), ConfigOrigin::Global(path.clone())));
+            }
+            if let Some(path) = sources.repo_path() {
+                files.push((path.clone(), ConfigOrigin::Repo(path)));
+            }
+        }
+    }
+
+    let mut ignored_keys = Vec::new();
+    for (path, origin) in files {
+        if path.exists() {
+            let mut layer = parse_layer(&path)?;
+            if matches!(origin, ConfigOrigin::Repo(_)) {
+                ignored_keys = drop_repo_secrets(&path, &mut layer);
+            }
+            merge_layer(&mut values, &mut origins, &layer, &origin);
+            if !matches!(origin, ConfigOrigin::Repo(_)) {
+                merge_values(&mut own_values, &layer);
+            }
+        }
+    }
+
+    // Profiles from every file are merged by now, a repo can extend a global one
+    let profiles: BTreeMap<String, Profile> = match values.get("profile") {
+        Some(profiles) => profiles.clone().try_into()?,
+        None => BTreeMap::new(),
+    };
+    let profile = select_profile(&profiles, sources)?;
+    if let Some(name) = &profile {
+        for (key, value) in flatten(&profiles[name].settings, "") {
+            if !matches!(origins.get(&format!("profile.{}.{}", name, key)), Some(ConfigOrigin::Repo(_))) {
+                set_dotted(&mut own_values, &key, value);
+            }
+        }
+        merge_layer(&mut values, &mut origins, &profiles[name].settings, &ConfigOrigin::Profile(name.clone()));
+    }
+
+    for (name, raw) in &sources.env {
+        if let Some((key, kind)) = CONFIG_KEYS.iter().find(|(key, _)| env_name(key) == *name) {
+            let value = parse_value(*kind, raw)
+                .and_then(|value| check_setting(key, value))
+                .map_err(|e| CommitError::ConfigError(format!("{}: {}", name, e)))?;
+            set_dotted(&mut own_values, key, value.clone());
+            set_dotted(&mut values, key, value);
+            origins.insert(key.to_string(), ConfigOrigin::Env(name.clone()));
+        }
+    }
+
+    for (key, value) in flatten(&sources.cli, "") {
+        check_setting(&key, value).map_err(|e| CommitError::ConfigError(format!("--{}: {}", key, e)))?;
+    }
+    merge_layer(&mut values, &mut origins, &sources.cli, &ConfigOrigin::Cli);
+    merge_values(&mut own_values, &sources.cli);
+
+    // Offline without a provider picked anywhere means the rule-based
+    // generator, a configured remote one is refused by `ApiSettings`
+    if values.get("offline").and_then(toml::Value::as_bool) == Some(true) && !origins.contains_key("provider") {
+        values.insert("provider".to_string(), Provider::None.to_string().into());
+    }
+    let withheld_key = withhold_key(&mut values, &mut origins, &own_values);
+
+    // Env and flags have no file to point at, name the setting instead
+    let config = toml::Value::Table(values.clone())
+        .try_into()
+        .map_err(|e: toml::de::Error| CommitError::ConfigError(format!("invalid setting: {}", e.message())))?;
+    Ok(LoadedConfig { config, profile, values, withheld_key, ignored_keys, origins })
+}
+
+/// A repo can point `base_url` or `provider` anywhere, the user's key must not
+/// follow it there. That includes keys the repo names itself, a `keyring:` or
+/// `env:` reference reads the user's secrets all the same. Repeating the
+/// endpoint the user's own settings give is fine.
+fn withhold_key(
+    values: &mut toml::Table,
+    origins: &mut HashMap<String, ConfigOrigin>,
+    own_values: &toml::Table,
+) -> Option<ConfigOrigin> {
+    let redirected = endpoint(values).is_some_and(|url| Some(&url) != endpoint(own_values).as_ref());
+    if !redirected || !values.contains_key("api_key") {
+        return None;
+    }
+    values.remove("api_key");
+    origins.remove("api_key")
+}
+
+/// Where requests go with these settings, `None` for no provider at all
+fn endpoint(values: &toml::Table) -> Option<String> {
+    let provider: Provider = values.get("provider")?.as_str()?.parse().ok()?;
+    let base_url = match values.get("base_url").and_then(toml::Value::as_str) {
+        Some(base_url) => base_url,
+        None => provider.default_base_url()?,
+    };
+    Some(base_url.trim_end_matches('/').to_string())
+}
+
+/// The `--profile` one, else the first by name whose `branches` match the
+/// current branch and whose `paths` match every changed path
+pub fn select_profile(profiles: &BTreeMap<String, Profile>, sources: &ConfigSources) -> Result<Option<String>> {
+    if let Some(name) = &sources.profile {
+        if !profiles.contains_key(name) {
+            let defined: Vec<&str> = profiles.keys().map(String::as_str).collect();
+            return Err(CommitError::ConfigError(match defined.as_slice() {
+                [] => format!("no profile named \"{}\", none are defined", name),
+                defined => format!("no profile named \"{}\", defined are {}", name, defined.join(", ")),
+            }));
+        }
+        return Ok(Some(name.clone()));
+    }
+
+    Ok(profiles
+        .iter()
+        .find(|(_, profile)| profile_matches(profile, sources.branch.as_deref(), &sources.changed_paths))
+        .map(|(name, _)| name.clone()))
+}
+
+fn profile_matches(profile: &Profile, branch: Option<&str>, changed_paths: &[String]) -> bool {
+    if profile.branches.is_empty() && profile.paths.is_empty() {
+        return false;
+    }
+    let branch_matches = profile.branches.is_empty() || branch.is_some_and(|branch| glob_set(&profile.branches).is_match(branch));
+    let paths_match = profile.paths.is_empty() || {
+        let paths = glob_set(&profile.paths);
+        !changed_paths.is_empty() && changed_paths.iter().all(|path| paths.is_match(path))
+    };
+    branch_matches && paths_match
+}
+
+/// `None` on a detached HEAD
+fn current_branch(repo: &git2::Repository) -> Option<String> {
+    let head = repo.head().ok()?;
+    if !head.is_branch() {
+        return None;
+    }
+    head.shorthand().map(str::to_string)
+}
+
+/// Checks every layer on its own, so each problem is reported against its source
+pub fn validate_config(sources: &ConfigSources) -> Vec<(String, Result<()>)> {
+    let mut results = Vec::new();
+
+    let files = match &sources.explicit_path {
+        Some(path) => vec![ConfigOrigin::Explicit(path.clone())],
+        None => sources
+            .global_path
+            .iter()
+            .map(|path| ConfigOrigin::Global(path.clone()))
+            .chain(sources.repo_path().map(ConfigOrigin::Repo))
+            .collect(),
+    };
+    for origin in files {
+        let path = match &origin {
+            ConfigOrigin::Global(path) | ConfigOrigin::Repo(path) | ConfigOrigin::Explicit(path) => path.clone(),
+            _ => continue,
+        };
+        if path.exists() {
+            results.push((origin.to_string(), read_layer(&path, &origin).map(|_| ())));
+        }
+    }
+
+    // Whatever env and flags add on top of valid files
+    if results.iter().all(|(_, result)| result.is_ok()) {
+        results.push(("environment and flags".to_string(), load_config(sources).map(|_| ())));
+    }
+    results
+}
+
+/// Runs a single setting through the schema
+pub fn check_setting(key: &str, value: toml::Value) -> std::result::Result<toml::Value, String> {
+    let mut table = toml::Table::new();
+    set_dotted(&mut table, key, value.clone());
+    toml::Value::Table(table)
+        .try_into::<Config>()
+        .map_err(|e| e.message().to_string())?;
+    Ok(value)
+}
+
+/// Parses one config file against the schema, errors carry its line numbers.
+/// Secrets a repo file may not hold are errors too.
+fn read_layer(path: &Path, origin: &ConfigOrigin) -> Result<toml::Table> {
+    let layer = parse_layer(path)?;
+    if matches!(origin, ConfigOrigin::Repo(_)) {
+        for (key, value) in flatten(&layer, "") {
+            check_repo_value(path, &key, &value)?;
+        }
+    }
+    Ok(layer)
+}
+
+fn parse_layer(path: &Path) -> Result<toml::Table> {
+    let content = fs::read_to_string(path)?;
+    let invalid = |e: toml::de::Error| CommitError::ConfigError(format!("{}: {}", path.display(), e));
+
+    toml::from_str::<Config>(&content).map_err(invalid)?;
+    toml::from_str(&content).map_err(invalid)
+}
+
+/// Takes the secrets `check_repo_value` refuses out of a repo layer, so a
+/// repo that committed its key still works, just without that key
+fn drop_repo_secrets(path: &Path, layer: &mut toml::Table) -> Vec<String> {
+    let refused: Vec<String> = flatten(layer, "")
+        .into_iter()
+        .filter(|(key, value)| check_repo_value(path, key, value).is_err())
+        .map(|(key, _)| key)
+        .collect();
+    for key in &refused {
+        remove_dotted(layer, key);
+    }
+    refused
+}
+
+/// Secrets in a file that can get committed must be references, and only
+/// ones that can't run commands or read other secrets
+pub fn check_repo_value(path: &Path, key: &str, value: &toml::Value) -> Result<()> {
+    let setting = profile_setting(key);
+    let reference = match value.as_str() {
+        Some(value) if SECRET_KEYS.contains(&setting) => SecretRef::parse(value),
+        _ => return Ok(()),
+    };
+
+    let problem = match reference {
+        None => "holds a plain key and could be committed",
+        Some(reference) if !reference.allowed_in_repo() => "may not run commands or read files and other variables",
+        Some(_) => return Ok(()),
+    };
+    Err(CommitError::ConfigError(format!(
+        "{} in {} {}, use a keyring: or env:{}* reference instead",
+        key,
+        path.display(),
+        problem,
+        ENV_PREFIX
+    )))
+}
+
+/// `~/.config/rusty-commit/config.toml`
+pub fn global_config_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".config").join("rusty-commit").join("config.toml"))
+}
+
+/// Parses and checks a value given on the command line for `key`
+pub fn parse_setting(key: &str, raw: &str) -> Result<toml::Value> {
+    let kind = check_key(key)?;
+    parse_value(kind, raw)
+        .and_then(|value| check_setting(key, value))
+        .map_err(|e| CommitError::ConfigError(format!("{}: {}", key, e)))
+}
+
+/// `default_model` for `profile.docs.default_model`, the key itself otherwise
+fn profile_setting(key: &str) -> &str {
+    match key.strip_prefix("profile.").and_then(|rest| rest.split_once('.')) {
+        Some((_, setting)) => setting,
+        None => key,
+    }
+}
+
+/// The type of a known setting, or an error suggesting the closest ones
+pub fn check_key(key: &str) -> Result<ValueKind> {
+    let setting = profile_setting(key);
+    CONFIG_KEYS
+        .iter()
+        .find(|(k, _)| *k == setting)
+        .map(|(_, kind)| *kind)
+        .ok_or_else(|| CommitError::ConfigError(unknown_key(setting)))
+}
+
+fn unknown_key(key: &str) -> String {
+    let closest = CONFIG_KEYS
+        .iter()
+        .map(|(k, _)| *k)
+        .filter(|k| edit_distance(k, key) <= 2 || k.ends_with(&format!("_{}", key)) || k.ends_with(&format!(".{}", key)))
+        .collect::<Vec<_>>();
+    match closest.as_slice() {
+        [] => format!("unknown setting \"{}\"", key),
+        keys => format!("unknown setting \"{}\", did you mean {}?", key, keys.join(" or ")),
+    }
+}
+
+/// Reads a single config file, checked against the schema
+pub fn read_config_file(path: &Path, origin: &ConfigOrigin) -> Result<toml::Table> {
+    read_layer(path, origin)
+}
+
+/// A setting from one config file only
+pub fn get_config_value(path: &Path, key: &str) -> Result<Option<toml::Value>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let table: toml::Table = toml::from_str(&fs::read_to_string(path)?)?;
+    Ok(get_dotted(&table, key).cloned())
+}
+
+/// Removes a setting from a config file, returns whether it was there
+pub fn unset_config_value(path: &Path, key: &str) -> Result<bool> {
+    if !path.exists() {
+        return Ok(false);
+    }
+    let mut table: toml::Table = toml::from_str(&fs::read_to_string(path)?)?;
+    let removed = remove_dotted(&mut table, key);
+    if removed {
+        fs::write(path, toml::to_string_pretty(&table)?)?;
+    }
+    Ok(removed)
+}
+
+/// Changes one setting in a config file, leaving the rest of it alone
+pub fn set_config_value(path: &Path, key: &str, value: toml::Value) -> Result<()> {
+    let mut table: toml::Table = if path.exists() {
+        toml::from_str(&fs::read_to_string(path)?)?
+    } else {
+        toml::Table::new()
+    };
+    set_dotted(&mut table, key, value);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, toml::to_string_pretty(&table)?)?;
     Ok(())
 }
 
-fn find_config_file(explicit_path: Option<&str>) -> Result<PathBuf> {
-    if let Some(path) = explicit_path {
-        return Ok(PathBuf::from(path));
+/// `cache.ttl_hours` is read from `RUSTY_COMMIT_CACHE_TTL_HOURS`
+pub fn env_name(key: &str) -> String {
+    format!("{}{}", ENV_PREFIX, key.replace('.', "_").to_uppercase())
+}
+
+pub fn parse_value(kind: ValueKind, raw: &str) -> std::result::Result<toml::Value, String> {
+    let raw = raw.trim();
+    match kind {
+        ValueKind::String => Ok(toml::Value::String(raw.to_string())),
+        ValueKind::Integer => raw
+            .parse()
+            .map(toml::Value::Integer)
+            .map_err(|_| format!("expected an integer, got \"{}\"", raw)),
+        ValueKind::Float => raw
+            .parse()
+            .map(toml::Value::Float)
+            .map_err(|_| format!("expected a number, got \"{}\"", raw)),
+        ValueKind::Bool => match raw {
+            "true" | "1" | "yes" => Ok(toml::Value::Boolean(true)),
+            "false" | "0" | "no" => Ok(toml::Value::Boolean(false)),
+            _ => Err(format!("expected true or false, got \"{}\"", raw)),
+        },
     }
+}
 
-    // Check current directory first
-    let local_config = PathBuf::from(".rusty-commit.toml");
-    if local_config.exists() {
-        return Ok(local_config);
+fn merge_values(values: &mut toml::Table, layer: &toml::Table) {
+    for (key, value) in flatten(layer, "") {
+        set_dotted(values, &key, value);
     }
+}
 
-    // Check global config
-    if let Some(home_dir) = dirs::home_dir() {
-        let global_config = home_dir.join(".config").join("rusty-commit").join("config.toml");
-        return Ok(global_config);
+fn merge_layer(
+    values: &mut toml::Table,
+    origins: &mut HashMap<String, ConfigOrigin>,
+    layer: &toml::Table,
+    origin: &ConfigOrigin,
+) {
+    for (key, value) in flatten(layer, "") {
+        set_dotted(values, &key, value);
+        origins.insert(key, origin.clone());
     }
+}
 
-    // Fallback to local config
-    Ok(local_config)
+/// Leaf values by dotted key, nested tables are walked into
+fn flatten(table: &toml::Table, prefix: &str) -> Vec<(String, toml::Value)> {
+    let mut leaves = Vec::new();
+    for (key, value) in table {
+        let key = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+        match value {
+            toml::Value::Table(nested) => leaves.extend(flatten(nested, &key)),
+            value => leaves.push((key, value.clone())),
+        }
+    }
+    leaves
 }
 
-impl TryFrom<toml::Value> for Config {
-    type Error = CommitError;
+fn get_dotted<'t>(table: &'t toml::Table, key: &str) -> Option<&'t toml::Value> {
+    match key.split_once('.') {
+        Some((head, rest)) => get_dotted(table.get(head)?.as_table()?, rest),
+        None => table.get(key),
+    }
+}
 
-    fn try_from(value: toml::Value) -> Result<Self> {
-        let table = value.as_table().ok_or_else(|| CommitError::ConfigError("Invalid config format".to_string()))?;
+/// Removes a key, and the table holding it once that's empty
+fn remove_dotted(table: &mut toml::Table, key: &str) -> bool {
+    match key.split_once('.') {
+        Some((head, rest)) => {
+            let removed = match table.get_mut(head) {
+                Some(toml::Value::Table(nested)) => remove_dotted(nested, rest),
+                _ => false,
+            };
+            if table.get(head).and_then(|v| v.as_table()).is_some_and(|t| t.is_empty()) {
+                table.remove(head);
+            }
+            removed
+        }
+        None => table.remove(key).is_some(),
+    }
+}
 
-        Ok(Config {
-            api_key: table.get("api_key").and_then(|v| v.as_str()).map(|s| s.to_string()),
-            default_model: table.get("default_model").and_then(|v| v.as_str()).unwrap_or("openai/gpt-3.5-turbo").to_string(),
-            max_diff_lines: table.get("max_diff_lines").and_then(|v| v.as_integer()).unwrap_or(1000) as usize,
-            temperature: table.get("temperature").and_then(|v| v.as_float()).unwrap_or(0.7) as f32,
-            max_tokens: table.get("max_tokens").and_then(|v| v.as_integer()).unwrap_or(150) as u32,
-        })
+pub(crate) fn set_dotted(table: &mut toml::Table, key: &str, value: toml::Value) {
+    match key.split_once('.') {
+        Some((head, rest)) => {
+            let nested = table
+                .entry(head)
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+            if !nested.is_table() {
+                *nested = toml::Value::Table(toml::Table::new());
+            }
+            if let toml::Value::Table(nested) = nested {
+                set_dotted(nested, rest, value);
+            }
+        }
+        None => {
+            table.insert(key.to_string(), value);
+        }
     }
-}
\ No newline at end of file
+}
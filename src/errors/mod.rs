@@ -30,6 +30,30 @@ pub enum CommitError {
     #[error("Git operation failed: {0}")]
     GitOperationError(String),
     
+    #[error("{hook} hook failed: {status}")]
+    HookFailed {
+        hook: String,
+        status: std::process::ExitStatus,
+    },
+
+    #[error("Monthly budget of {limit:.2} exceeded ({spent:.2} spent)")]
+    BudgetExceeded {
+        spent: f64,
+        limit: f64,
+    },
+
+    #[error("Could not resolve secret: {0}")]
+    SecretError(String),
+
+    #[error("Offline mode: {0}")]
+    OfflineViolation(String),
+
+    #[error("Background task failed: {0}")]
+    TaskFailed(#[from] tokio::task::JoinError),
+
+    #[error("Input required but prompts are disabled (--no-input or no terminal): {0}")]
+    InputRequired(String),
+
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
     
@@ -46,4 +70,14 @@ pub enum CommitError {
     TomlSerError(#[from] toml::ser::Error),
 }
 
+impl CommitError {
+    /// Process exit status, so scripts can tell a missing answer from a failure
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            CommitError::InputRequired(_) => 3,
+            _ => 1,
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, CommitError>;
\ No newline at end of file
@@ -1,7 +1,37 @@
-use dialoguer::{Input, Confirm, theme::ColorfulTheme};
-use crate::errors::Result;
+use colored::Colorize;
+use dialoguer::{Input, Confirm, MultiSelect, Select, theme::ColorfulTheme};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::api::models::ModelInfo;
+use crate::config::{MessageStyle, Provider};
+use crate::errors::{CommitError, Result};
+use crate::utils::fuzzy::fuzzy_filter;
+use crate::utils::hunks::Hunk;
+use crate::utils::split::{SplitGroup, SplitPlan};
+
+static PROMPTS_DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Makes every prompt fail with `InputRequired` instead of waiting for an
+/// answer that will never come, for CI, hooks and editor integrations
+pub fn disable_prompts() {
+    PROMPTS_DISABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn prompts_enabled() -> bool {
+    !PROMPTS_DISABLED.load(Ordering::Relaxed)
+}
+
+/// `what` names the missing answer and how to supply it without a prompt
+pub fn require_input(what: &str) -> Result<()> {
+    if prompts_enabled() {
+        return Ok(());
+    }
+    Err(CommitError::InputRequired(what.to_string()))
+}
 
 pub fn prompt_commit_message(current_message: &str) -> Result<String> {
+    require_input("editing the message, drop --interactive")?;
     println!("Rusty  Generated message: {}", current_message);
     
     if Confirm::with_theme(&ColorfulTheme::default())
@@ -22,6 +52,7 @@ pub fn prompt_commit_message(current_message: &str) -> Result<String> {
 }
 
 pub fn confirm_commit(message: &str) -> Result<bool> {
+    require_input("commit confirmation, pass --yes")?;
     println!("📝 Ready to commit with message: \"{}\"", message);
     
     Ok(Confirm::with_theme(&ColorfulTheme::default())
@@ -30,10 +61,265 @@ pub fn confirm_commit(message: &str) -> Result<bool> {
         .interact()?)
 }
 
+/// Shows what went wrong and asks whether to have another go, never when
+/// prompts are disabled
+pub fn confirm_retry(error: &str, prompt: &str) -> Result<bool> {
+    // Nobody to ask, the caller reports the error itself
+    if !prompts_enabled() {
+        return Ok(false);
+    }
+    println!("❌ {}", error);
+
+    Ok(Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .default(true)
+        .interact()?)
+}
+
 pub fn prompt_api_key() -> Result<String> {
+    require_input("an API key, set api_key or RUSTY_COMMIT_API_KEY")?;
     let api_key = Input::<String>::with_theme(&ColorfulTheme::default())
         .with_prompt("Enter your OpenRouter API key")
         .interact()?;
     
     Ok(api_key)
 }
+
+pub fn select_provider(current: Provider) -> Result<Provider> {
+    require_input("answers to the init wizard, use config set instead")?;
+    let labels = [
+        "OpenRouter (hosted models, needs an API key)",
+        "Local (Ollama, llama.cpp or another OpenAI-compatible server)",
+        "None (rule-based messages, nothing leaves this machine)",
+    ];
+    let selected = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Which provider should write your messages?")
+        .items(&labels)
+        .default(Provider::ALL.iter().position(|p| *p == current).unwrap_or(0))
+        .interact()?;
+
+    Ok(Provider::ALL[selected])
+}
+
+pub fn prompt_base_url(current: &str) -> Result<String> {
+    require_input("answers to the init wizard, use config set instead")?;
+    Ok(Input::<String>::with_theme(&ColorfulTheme::default())
+        .with_prompt("Server URL")
+        .default(current.to_string())
+        .interact_text()?)
+}
+
+/// Picks from the provider's models, or lets the user type one in. Long
+/// lists get narrowed down with a fuzzy search first.
+pub fn select_model(models: &[ModelInfo], current: &str) -> Result<String> {
+    require_input("answers to the init wizard, use config set instead")?;
+    let theme = ColorfulTheme::default();
+    let mut shown: Vec<&ModelInfo> = models.iter().collect();
+    if models.len() > 15 {
+        let search = Input::<String>::with_theme(&theme)
+            .with_prompt(format!("Search {} models (empty lists them all)", models.len()))
+            .allow_empty(true)
+            .interact_text()?;
+        if !search.trim().is_empty() {
+            shown = fuzzy_filter(&search, models, |model| model.id.as_str());
+        }
+    }
+
+    let mut labels: Vec<String> = shown
+        .iter()
+        .map(|model| match model.context_length {
+            Some(context) => format!("{} ({}k context)", model.id, context / 1000),
+            None => model.id.clone(),
+        })
+        .collect();
+    labels.push("Other...".to_string());
+
+    let selected = Select::with_theme(&theme)
+        .with_prompt("Default model")
+        .items(&labels)
+        .default(shown.iter().position(|m| m.id == current).unwrap_or(0))
+        .max_length(15)
+        .interact()?;
+
+    match shown.get(selected) {
+        Some(model) => Ok(model.id.clone()),
+        None => Ok(Input::<String>::with_theme(&theme)
+            .with_prompt("Model id")
+            .default(current.to_string())
+            .interact_text()?),
+    }
+}
+
+pub fn select_message_style(current: MessageStyle) -> Result<MessageStyle> {
+    require_input("answers to the init wizard, use config set instead")?;
+    let labels = [
+        "Conventional   feat(parser): handle empty input",
+        "Gitmoji        ✨ Handle empty input in the parser",
+        "Plain          Handle empty input in the parser",
+    ];
+    let selected = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Message style")
+        .items(&labels)
+        .default(MessageStyle::ALL.iter().position(|s| *s == current).unwrap_or(0))
+        .interact()?;
+
+    Ok(MessageStyle::ALL[selected])
+}
+
+/// `true` for the repository's file, `false` for the global one
+pub fn select_config_target(global: &Path, repo: &Path) -> Result<bool> {
+    require_input("answers to the init wizard, use config set instead")?;
+    let labels = [
+        format!("Global, for every repository ({})", global.display()),
+        format!("This repository only ({})", repo.display()),
+    ];
+    let selected = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Where should these settings go?")
+        .items(&labels)
+        .default(0)
+        .interact()?;
+
+    Ok(selected == 1)
+}
+
+pub fn confirm_install_hook() -> Result<bool> {
+    require_input("answers to the init wizard, use config set instead")?;
+    Ok(Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt("Install a prepare-commit-msg hook so plain `git commit` gets a generated message?")
+        .default(false)
+        .interact()?)
+}
+
+/// Lets the user edit messages and move hunks between commits. Returns `None` when cancelled.
+pub fn review_split_plan(mut plan: SplitPlan, hunks: &[Hunk]) -> Result<Option<SplitPlan>> {
+    require_input("reviewing the split plan, pass --yes to accept it")?;
+    let theme = ColorfulTheme::default();
+    let by_id: HashMap<&str, &Hunk> = hunks.iter().map(|h| (h.id.as_str(), h)).collect();
+    let describe = |id: &String| match by_id.get(id.as_str()) {
+        Some(hunk) => format!("{} (+{} -{})", id, hunk.additions, hunk.deletions),
+        None => id.clone(),
+    };
+
+    loop {
+        println!("\n📦 Proposed commits:");
+        for (i, group) in plan.commits.iter().enumerate() {
+            println!("  {}. {}", i + 1, group.message);
+            for id in &group.hunks {
+                println!("       {}", describe(id));
+            }
+        }
+
+        let action = Select::with_theme(&theme)
+            .with_prompt("What next?")
+            .items(&["Create commits", "Edit a message", "Move changes", "Cancel"])
+            .default(0)
+            .interact()?;
+
+        match action {
+            0 => return Ok(Some(plan)),
+            1 => {
+                let i = select_commit(&theme, &plan, "Which commit?")?;
+                plan.commits[i].message = Input::<String>::with_theme(&theme)
+                    .with_prompt("Commit message")
+                    .with_initial_text(&plan.commits[i].message)
+                    .interact_text()?;
+            }
+            2 => {
+                let from = select_commit(&theme, &plan, "Move changes from which commit?")?;
+                let labels: Vec<String> = plan.commits[from].hunks.iter().map(describe).collect();
+                let picked = MultiSelect::with_theme(&theme)
+                    .with_prompt("Changes to move (space to select)")
+                    .items(&labels)
+                    .interact()?;
+                if picked.is_empty() {
+                    continue;
+                }
+
+                let mut targets: Vec<String> = plan.commits.iter().map(|g| g.message.clone()).collect();
+                targets.push("New commit".to_string());
+                let to = Select::with_theme(&theme)
+                    .with_prompt("Move them to")
+                    .items(&targets)
+                    .default(0)
+                    .interact()?;
+
+                let moved: Vec<String> = picked.iter().map(|&i| plan.commits[from].hunks[i].clone()).collect();
+                plan.commits[from].hunks.retain(|id| !moved.contains(id));
+
+                if to == plan.commits.len() {
+                    let message = Input::<String>::with_theme(&theme)
+                        .with_prompt("Commit message")
+                        .interact_text()?;
+                    plan.commits.push(SplitGroup { message, hunks: moved });
+                } else {
+                    plan.commits[to].hunks.extend(moved);
+                }
+                plan.commits.retain(|g| !g.hunks.is_empty());
+            }
+            _ => return Ok(None),
+        }
+    }
+}
+
+fn select_commit(theme: &ColorfulTheme, plan: &SplitPlan, prompt: &str) -> Result<usize> {
+    let messages: Vec<&str> = plan.commits.iter().map(|g| g.message.as_str()).collect();
+    Ok(Select::with_theme(theme)
+        .with_prompt(prompt)
+        .items(&messages)
+        .default(0)
+        .interact()?)
+}
+
+/// `git add -p` style walk over the hunks, returns the ones picked for staging
+pub fn pick_hunks(hunks: &[Hunk]) -> Result<Vec<&Hunk>> {
+    require_input("picking hunks, stage them with git add instead of --patch")?;
+    let theme = ColorfulTheme::default();
+    let mut picked = Vec::new();
+    let mut skip_file: Option<&str> = None;
+    let mut take_file: Option<&str> = None;
+
+    for (i, hunk) in hunks.iter().enumerate() {
+        if skip_file == Some(hunk.path.as_str()) {
+            continue;
+        }
+        if take_file == Some(hunk.path.as_str()) {
+            picked.push(hunk);
+            continue;
+        }
+
+        println!("\n{} {}", format!("[{}/{}]", i + 1, hunks.len()).dimmed(), hunk.path.bold());
+        for line in hunk.text.lines() {
+            match line.chars().next() {
+                Some('+') => println!("{}", line.green()),
+                Some('-') => println!("{}", line.red()),
+                Some('@') => println!("{}", line.cyan()),
+                _ => println!("{}", line),
+            }
+        }
+
+        let answer = Select::with_theme(&theme)
+            .with_prompt("Stage this hunk?")
+            .items(&[
+                "y - stage this hunk",
+                "n - skip this hunk",
+                "a - stage this and the rest of the file",
+                "d - skip the rest of the file",
+                "q - stop here",
+            ])
+            .default(0)
+            .interact()?;
+
+        match answer {
+            0 => picked.push(hunk),
+            1 => {}
+            2 => {
+                picked.push(hunk);
+                take_file = Some(&hunk.path);
+            }
+            3 => skip_file = Some(&hunk.path),
+            _ => break,
+        }
+    }
+
+    Ok(picked)
+}
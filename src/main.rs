@@ -1,139 +1,298 @@
-mod cli;
-mod api;
-mod config;
-mod errors;
-mod utils;
-mod interactive;
-
 use clap::Parser;
 use colored::Colorize;
 use indicatif::{ProgressBar, ProgressStyle};
-use rusty_commit::api::openrouter::generate_commit_message_openrouter;
-use rusty_commit::utils::git::{get_git_changes, filter_diff_content, perform_git_commit, generate_fallback_message};
-use rusty_commit::config::{load_config, save_config};
-use rusty_commit::interactive::{prompt_commit_message, confirm_commit, prompt_api_key};
+use rusty_commit::cli;
+use rusty_commit::api::openrouter::{generate_commit_message_openrouter, generate_split_plan_openrouter};
+use rusty_commit::api::cache::clear_cache;
+use rusty_commit::api::models::{check_api_key, load_models, ModelCatalog, ModelInfo};
+use rusty_commit::api::provider::ApiSettings;
+use rusty_commit::api::usage::{budget_status, TallyState, month_to_date_cost, read_ledger, totals_by, UsageTotals};
+use rusty_commit::api::summarize::generate_commit_message_map_reduce;
+use rusty_commit::utils::budget::{budget_diff, split_into_chunks, DiffBudget, MAP_REDUCE_FACTOR};
+use rusty_commit::utils::diff::DiffSummary;
+use rusty_commit::utils::output::{ErrorReport, MessageReport, ReportMessage, ReportStats, ReportTokens, REPORT_SCHEMA_VERSION};
+use rusty_commit::utils::symbols::{summarize_symbols, symbols_prompt_text, SymbolChange};
+use rusty_commit::utils::git::{get_git_changes, perform_git_commit, CommitOptions};
+use rusty_commit::utils::fallback::generate_fallback_message;
+use rusty_commit::config::{
+    check_key, check_repo_value, get_config_value, load_config, parse_setting, read_config_file, set_config_value,
+    unset_config_value, validate_config, BudgetAction, ConfigOrigin, ConfigSources, LoadedConfig, Provider, CONFIG_KEYS, CONFIG_VERSION,
+    SECRET_KEYS,
+};
+use rusty_commit::errors::CommitError;
+use std::io::IsTerminal;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+use std::path::{Path, PathBuf};
+use rusty_commit::utils::secrets::{is_secret_ref, store_in_keyring, DEFAULT_KEYRING_ACCOUNT};
+use rusty_commit::utils::fuzzy::{fuzzy_filter, suggestions};
+use rusty_commit::utils::hooks::install_hook;
+use rusty_commit::utils::hunks::{collect_unstaged_hunks, stage_hunks};
+use rusty_commit::utils::last_message::{clear_last_message, commit_state, load_last_message, save_last_message};
+use rusty_commit::utils::split::{collect_staged_hunks, create_split_commits, fallback_split_plan, normalize_split_plan};
+use rusty_commit::interactive::{
+    confirm_commit, confirm_install_hook, disable_prompts, require_input, confirm_retry, pick_hunks, prompt_api_key, prompt_base_url, prompt_commit_message,
+    review_split_plan, select_config_target, select_message_style, select_model, select_provider,
+};
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn main() -> ExitCode {
     let cli = cli::Cli::parse();
+    let json = cli.format == cli::OutputFormat::Json;
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            let code = e.downcast_ref::<CommitError>().map_or(1, CommitError::exit_code);
+            if json {
+                // Scripts read stdout, failures included
+                let report = ErrorReport::new(e.to_string(), code);
+                println!("{}", serde_json::to_string_pretty(&report).unwrap_or_default());
+            } else {
+                eprintln!("{} {}", "❌".red(), e);
+            }
+            ExitCode::from(code)
+        }
+    }
+}
+
+async fn run(cli: cli::Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let json = cli.format == cli::OutputFormat::Json;
+    
+    // CI, hooks and editors can't answer, fail instead of hanging
+    if json || cli.no_input || !std::io::stdin().is_terminal() {
+        disable_prompts();
+    }
+    
+    // Repo settings come from the repository root, wherever we were started
+    let repo = git2::Repository::discover(".").ok();
     
-    // Load configuration
-    let mut config = load_config(cli.config.as_deref())?;
+    // Load configuration: defaults, global, repo, env, then CLI flags
+    let mut sources = ConfigSources::discover(cli.config.as_deref(), repo.as_ref(), cli.config_overrides());
+    sources.profile = cli.profile.clone();
     
-    // Override config with CLI args
-    config.default_model = cli.model;
-    if let Some(api_key) = &cli.api_key {
-        config.api_key = Some(api_key.clone());
+    // Config commands load what they need themselves, so a broken file can still be fixed
+    if let Some(cli::Commands::Config { config_command }) = &cli.command {
+        return run_config_command(config_command, &sources).await;
     }
     
-    // Get API key
-    let api_key = if let Some(key) = config.api_key.clone() {
-        key
-    } else {
-        println!("🔑 API key not found in config.");
-        let key = prompt_api_key()?;
-        
-        // Save to config
-        config.api_key = Some(key.clone());
-        if let Err(e) = save_config(&config, cli.config.as_deref()) {
-            println!("⚠️  Warning: Could not save API key to config: {}", e);
+    if let Some(cli::Commands::Init) = &cli.command {
+        return run_init(&sources, repo.as_ref()).await;
+    }
+    
+    let loaded = load_config(&sources)?;
+    let config = loaded.config.clone();
+    
+    // Cache maintenance needs neither a key nor a repository
+    if let Some(cli::Commands::Cache { cache_command }) = &cli.command {
+        match cache_command {
+            cli::CacheCommands::Clear => {
+                let removed = clear_cache()?;
+                println!("🧹 Removed {} cached response{}", removed, if removed == 1 { "" } else { "s" });
+            }
         }
-        
-        key
-    };
+        return Ok(());
+    }
+    
+    if let Some(cli::Commands::Models { models_command }) = &cli.command {
+        match models_command {
+            cli::ModelsCommands::List { search, min_context, max_price, free, capability, refresh } => {
+                let catalog = load_models(&ApiSettings::from_config(&config)?, *refresh).await?;
+                let mut models: Vec<&ModelInfo> = match search {
+                    Some(search) => fuzzy_filter(search, &catalog.models, |model| model.id.as_str()),
+                    None => catalog.models.iter().collect(),
+                };
+                models.retain(|model| {
+                    min_context.is_none_or(|min| model.context_length.is_some_and(|context| context >= min))
+                        && max_price.is_none_or(|max| model.price_per_million().is_some_and(|(prompt, _)| prompt <= max))
+                        && (!free || model.is_free())
+                        && capability.iter().all(|wanted| model.capabilities().contains(&wanted.as_str()))
+                });
+                print_models(&catalog, &models, &config);
+            }
+        }
+        return Ok(());
+    }
+    
+    if let Some(cli::Commands::Usage { by }) = &cli.command {
+        print_usage(&config, *by)?;
+        return Ok(());
+    }
     
     // Open git repository
-    let repo = git2::Repository::open(".")?;
+    let repo = match repo {
+        Some(repo) => repo,
+        None => git2::Repository::discover(".")?,
+    };
     
+    if let Some(cli::Commands::InstallHook) = &cli.command {
+        let hook = install_hook(&repo)?;
+        println!("🪝 Installed {}", hook.display());
+        return Ok(());
+    }
+    
+    // Path profiles can change the provider and key, pick them before checking either
+    sources.changed_paths = get_git_changes(&repo)?.commit_files().to_vec();
+    let loaded = load_config(&sources)?;
+    let config = loaded.config.clone();
+    
+    // Refuse to start rather than leak the diff
+    if config.offline {
+        ApiSettings::from_config(&config)?;
+    }
+    
+    // First run: only OpenRouter needs a key, walk through the whole setup
+    if config.api_key.is_none() && loaded.withheld_key.is_none() && config.provider == Provider::OpenRouter && !config.offline {
+        require_input("an API key, run `rusty-commit init` or set RUSTY_COMMIT_API_KEY")?;
+        println!("🔑 No API key configured yet.");
+        run_init(&sources, Some(&repo)).await?;
+    }
+    
+    // Let the user pick what goes into the commit, other commands have no use for it
+    let writes_message = matches!(&cli.command, Some(cli::Commands::Generate { .. } | cli::Commands::Commit { .. }))
+        || (cli.command.is_none() && cli.dry_run);
+    if cli.patch && writes_message {
+        let hunks = collect_unstaged_hunks(&repo)?;
+        if hunks.is_empty() {
+            if !json {
+                println!("{}", "📭 No unstaged hunks to pick from.".yellow());
+            }
+        } else {
+            let picked = pick_hunks(&hunks)?;
+            if picked.is_empty() {
+                println!("❌ No hunks selected.");
+                return Ok(());
+            }
+            stage_hunks(&repo, &picked)?;
+        }
+    }
+
     // Get git changes
-    if cli.verbose {
+    if cli.verbose && !json {
         println!("🔍 Analyzing git changes...");
     }
     
     let changes = get_git_changes(&repo)?;
     
     if changes.staged_files.is_empty() && changes.unstaged_files.is_empty() {
+        if json {
+            return Err(CommitError::NoChanges.into());
+        }
         println!("{}", "📭 No changes to commit.".yellow());
         return Ok(());
     }
     
     // Determine which diff to use
-    let (diff_content, files) = if !changes.staged_diff.is_empty() {
+    let has_staged = !changes.staged_diff.is_empty();
+    let (diff, files) = if !changes.staged_diff.is_empty() {
         (changes.staged_diff, changes.staged_files)
     } else {
         (changes.unstaged_diff, changes.unstaged_files)
     };
     
-    let filtered_diff = filter_diff_content(&diff_content, config.max_diff_lines);
+    // Picking hunks may have changed what's being committed
+    sources.changed_paths = files.clone();
+    let loaded = load_config(&sources)?;
+    let config = loaded.config.clone();
+    if !json {
+        warn_unused_keys(&loaded);
+    }
+    
+    let diff_content = diff.to_prompt_text();
+    let budget = DiffBudget::for_model(&config.default_model, config.max_tokens, config.max_diff_lines);
+    let filtered_diff = budget_diff(&diff, &budget);
+    
+    let symbol_changes = summarize_symbols(&repo, &diff);
+    let symbols = symbols_prompt_text(&symbol_changes);
     
-    if cli.verbose {
+    // Far beyond the context: summarize the parts first
+    let chunks = if budget.estimate_tokens(&diff_content) > budget.max_tokens.saturating_mul(MAP_REDUCE_FACTOR) {
+        split_into_chunks(&diff, &budget)
+    } else {
+        Vec::new()
+    };
+    
+    if cli.verbose && !json {
+        if let Some(profile) = &loaded.profile {
+            println!("🎛️  Profile: {}", profile);
+        }
+        if config.offline {
+            println!("🔒 Offline: nothing leaves this machine (provider: {})", config.provider);
+        }
         println!("📁 Files changed: {}", files.join(", "));
-        println!("📊 Diff size: {} lines (filtered to {})", 
+        println!("📊 Diff size: {} lines (filtered to {}, ~{} of {} tokens)", 
                 diff_content.lines().count(), 
-                filtered_diff.lines().count());
+                filtered_diff.lines().count(),
+                budget.estimate_tokens(&filtered_diff),
+                budget.max_tokens);
+        if !symbols.is_empty() {
+            println!("🔣 Symbols changed:\n{}", symbols);
+        }
+        if chunks.len() > 1 {
+            println!("🧩 Diff exceeds the model context, summarizing it in {} parts", chunks.len());
+        }
     }
     
+    if config.provider != Provider::None && config.budget.action == BudgetAction::Warn && !json {
+        if let Some((spent, limit)) = budget_status(&config.budget, &read_ledger()?) {
+            println!("{}", format!("⚠️  Monthly budget of {:.2} exceeded ({:.2} spent)", limit, spent).yellow());
+        }
+    }
+    
+    let inputs = MessageInputs {
+        repo: &repo,
+        diff: &diff,
+        files: &files,
+        filtered_diff: &filtered_diff,
+        symbols: &symbols,
+        symbol_changes: &symbol_changes,
+        chunks: &chunks,
+        budget: &budget,
+    };
+    
     // Handle different commands
     match &cli.command {
-        Some(cli::Commands::Generate) | None if cli.dry_run => {
+        command @ (Some(cli::Commands::Generate { .. }) | None) if command.is_some() || cli.dry_run => {
             // Generate message only
-            let pb = ProgressBar::new_spinner();
-            pb.set_style(ProgressStyle::default_spinner().template("{spinner:.green} {msg}").unwrap());
-            pb.set_message("🤖 Generating commit message...");
-            pb.enable_steady_tick(std::time::Duration::from_millis(100));
+            let api = ApiSettings::from_config(&config)?.for_repo(&repo);
+            let generated = generate_or_fallback(&inputs, &api, json).await;
+            let commit_message = &generated.message;
             
-            let commit_message = match generate_commit_message_openrouter(
-                &repo, 
-                &files, 
-                &filtered_diff, 
-                &api_key, 
-                &config.default_model
-            ).await {
-                Ok(message) => message,
-                Err(e) => {
-                    pb.finish_with_message("❌ API failed, using fallback");
-                    println!("⚠️  API failed: {}. Using intelligent fallback...", e);
-                    generate_fallback_message(&files, &filtered_diff)
-                }
-            };
+            if json {
+                let report = message_report("generate", &generated, &inputs, &loaded);
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                println!("📝 Generated message: {}", commit_message.green());
+            }
+            
+            // Let `commit` pick it up instead of asking again
+            save_last_message(&repo, &commit_state(&repo, !has_staged)?, commit_message)?;
             
-            pb.finish_with_message("✅ Message generated");
-            println!("📝 Generated message: {}", commit_message.green());
+            // Keep git's commented status below the message
+            if let Some(cli::Commands::Generate { message_file: Some(path) }) = command {
+                let existing = std::fs::read_to_string(path).unwrap_or_default();
+                std::fs::write(path, format!("{}\n{}", commit_message.trim_end(), existing))?;
+            }
         }
         
         Some(cli::Commands::Commit { message }) => {
-            let commit_message = if let Some(msg) = message {
-                msg.clone()
+            let last_message = load_last_message(&repo, &commit_state(&repo, !has_staged)?);
+            let generated = if let Some(msg) = message {
+                Generated::given(msg.clone(), "argument")
+            } else if let Some(msg) = last_message {
+                if !json {
+                    println!("♻️  Reusing the message generated for these changes");
+                }
+                Generated::given(msg, "previous")
             } else {
-                // Generate message
-                let pb = ProgressBar::new_spinner();
-                pb.set_style(ProgressStyle::default_spinner().template("{spinner:.green} {msg}").unwrap());
-                pb.set_message("🤖 Generating commit message...");
-                pb.enable_steady_tick(std::time::Duration::from_millis(100));
-                
-                let msg = match generate_commit_message_openrouter(
-                    &repo, 
-                    &files, 
-                    &filtered_diff, 
-                    &api_key, 
-                    &config.default_model
-                ).await {
-                    Ok(message) => message,
-                    Err(e) => {
-                        pb.finish_with_message("❌ API failed, using fallback");
-                        println!("⚠️  API failed: {}. Using intelligent fallback...", e);
-                        generate_fallback_message(&files, &filtered_diff)
-                    }
-                };
-                pb.finish_with_message("✅ Message generated");
-                msg
+                let api = ApiSettings::from_config(&config)?.for_repo(&repo);
+                generate_or_fallback(&inputs, &api, json).await
             };
             
             // Interactive mode
             let final_message = if cli.interactive {
-                prompt_commit_message(&commit_message)?
+                prompt_commit_message(&generated.message)?
             } else {
-                commit_message
+                generated.message.clone()
             };
             
             // Confirm before committing
@@ -143,15 +302,27 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
             
             // Perform commit
-            let pb = ProgressBar::new_spinner();
-            pb.set_style(ProgressStyle::default_spinner().template("{spinner:.green} {msg}").unwrap());
-            pb.set_message("💾 Committing changes...");
-            pb.enable_steady_tick(std::time::Duration::from_millis(100));
+            let pb = spinner("💾 Committing changes...", json);
             
-            match perform_git_commit(&repo, &final_message) {
-                Ok(_) => {
+            // Commit exactly what the message was generated from
+            let commit_options = CommitOptions {
+                no_verify: cli.no_verify,
+                backend: config.commit_backend,
+                stage_all: !has_staged,
+            };
+
+            match perform_git_commit(&repo, &final_message, &commit_options) {
+                Ok(oid) => {
+                    clear_last_message(&repo);
                     pb.finish_with_message("✅ Commit successful");
-                    println!("🎉 Committed with message: {}", final_message.green());
+                    if json {
+                        let mut report = message_report("commit", &generated, &inputs, &loaded);
+                        report.message = ReportMessage::new(&final_message);
+                        report.commit_oid = Some(oid.to_string());
+                        println!("{}", serde_json::to_string_pretty(&report)?);
+                    } else {
+                        println!("🎉 Committed with message: {}", final_message.green());
+                    }
                 }
                 Err(e) => {
                     pb.finish_with_message("❌ Commit failed");
@@ -160,35 +331,66 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         }
         
-        Some(cli::Commands::Config { config_command }) => {
-            match config_command {
-                cli::ConfigCommands::SetApiKey => {
-                    let key = prompt_api_key()?;
-                    config.api_key = Some(key);
-                    save_config(&config, cli.config.as_deref())?;
-                    println!("✅ API key saved to config");
+        Some(cli::Commands::Split) => {
+            if !has_staged {
+                println!("{}", "📭 Nothing staged to split. Stage changes with git add first.".yellow());
+                return Ok(());
+            }
+
+            let hunks = collect_staged_hunks(&repo)?;
+
+            let pb = spinner("🤖 Planning commits...", json);
+
+            let api = ApiSettings::from_config(&config)?.for_repo(&repo);
+            let plan = if !api.uses_model() {
+                pb.finish_with_message("✅ Grouped changes by directory");
+                fallback_split_plan(&hunks, &diff, &symbol_changes, api.message_style)
+            } else {
+                match generate_split_plan_openrouter(&hunks, &api).await {
+                    Ok(plan) => {
+                        pb.finish_with_message("✅ Plan generated");
+                        normalize_split_plan(plan, &hunks, &diff, &symbol_changes, api.message_style)
+                    }
+                    Err(e) => {
+                        pb.finish_with_message("❌ API failed, using fallback");
+                        println!("⚠️  API failed: {}. Grouping changes by directory...", e);
+                        fallback_split_plan(&hunks, &diff, &symbol_changes, api.message_style)
+                    }
                 }
-                cli::ConfigCommands::SetModel { model } => {
-                    config.default_model = model.clone();
-                    save_config(&config, cli.config.as_deref())?;
-                    println!("✅ Default model set to: {}", model);
+            };
+
+            // Review the plan
+            let plan = if cli.force {
+                plan
+            } else {
+                match review_split_plan(plan, &hunks)? {
+                    Some(plan) => plan,
+                    None => {
+                        println!("❌ Split cancelled.");
+                        return Ok(());
+                    }
                 }
-                cli::ConfigCommands::Show => {
-                    println!("📋 Current configuration:");
-                    println!("  API Key: {}", if config.api_key.is_some() { "Set" } else { "Not set" });
-                    println!("  Default Model: {}", config.default_model);
-                    println!("  Max Diff Lines: {}", config.max_diff_lines);
-                    println!("  Temperature: {}", config.temperature);
-                    println!("  Max Tokens: {}", config.max_tokens);
+            };
+
+            let commit_options = CommitOptions {
+                no_verify: cli.no_verify,
+                backend: config.commit_backend,
+                stage_all: false,
+            };
+
+            match create_split_commits(&repo, &plan, &hunks, &commit_options) {
+                Ok(commits) => {
+                    for (oid, group) in commits {
+                        println!("🎉 {} {}", &oid.to_string()[..7], group.message.green());
+                    }
+                }
+                Err(e) => {
+                    println!("❌ Split failed");
+                    return Err(e.into());
                 }
             }
         }
-        
-        Some(cli::Commands::InstallHook) => {
-            println!("🚧 Hook installation not implemented yet");
-            // TODO: Implement pre-commit hook installation
-        }
-        
+
         _ => {
             println!("Use --help for usage information");
         }
@@ -196,3 +398,535 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     
     Ok(())
 }
+
+/// What the staged or unstaged changes look like, for writing a message
+struct MessageInputs<'a> {
+    repo: &'a git2::Repository,
+    diff: &'a DiffSummary,
+    files: &'a [String],
+    filtered_diff: &'a str,
+    symbols: &'a str,
+    symbol_changes: &'a [SymbolChange],
+    chunks: &'a [DiffSummary],
+    budget: &'a DiffBudget,
+}
+
+/// A message and how it came about
+struct Generated {
+    message: String,
+    /// `model`, `fallback`, `previous` or `argument`, see `MessageReport`
+    source: &'static str,
+    /// Why the model failed, `None` when there was no model to ask
+    fallback_reason: Option<String>,
+    latency: Duration,
+    usage: TallyState,
+}
+
+impl Generated {
+    fn given(message: String, source: &'static str) -> Self {
+        Self { message, source, fallback_reason: None, latency: Duration::ZERO, usage: TallyState::default() }
+    }
+}
+
+fn spinner(message: &'static str, hidden: bool) -> ProgressBar {
+    if hidden {
+        return ProgressBar::hidden();
+    }
+    let pb = ProgressBar::new_spinner();
+    pb.set_style(ProgressStyle::default_spinner().template("{spinner:.green} {msg}").unwrap());
+    pb.set_message(message);
+    pb.enable_steady_tick(Duration::from_millis(100));
+    pb
+}
+
+async fn generate_message(inputs: &MessageInputs<'_>, api: &ApiSettings) -> rusty_commit::errors::Result<String> {
+    if inputs.chunks.len() > 1 {
+        generate_commit_message_map_reduce(inputs.files, inputs.chunks, inputs.symbols, inputs.budget, api).await
+    } else {
+        generate_commit_message_openrouter(inputs.repo, inputs.files, inputs.filtered_diff, inputs.symbols, api).await
+    }
+}
+
+/// Asks the model, falling back to the offline generator when there is none or it fails
+async fn generate_or_fallback(inputs: &MessageInputs<'_>, api: &ApiSettings, quiet: bool) -> Generated {
+    let pb = spinner("🤖 Generating commit message...", quiet);
+    let started = Instant::now();
+    let (message, source, fallback_reason) = if !api.uses_model() {
+        (generate_fallback_message(inputs.diff, inputs.symbol_changes, api.message_style), "fallback", None)
+    } else {
+        match generate_message(inputs, api).await {
+            Ok(message) => (message, "model", None),
+            Err(e) => {
+                pb.finish_with_message("❌ API failed, using fallback");
+                if !quiet {
+                    println!("⚠️  API failed: {}. Using intelligent fallback...", e);
+                }
+                (generate_fallback_message(inputs.diff, inputs.symbol_changes, api.message_style), "fallback", Some(e.to_string()))
+            }
+        }
+    };
+    pb.finish_with_message("✅ Message generated");
+
+    Generated { message, source, fallback_reason, latency: started.elapsed(), usage: api.tally.state() }
+}
+
+/// The `--format json` report for a message about to be printed or committed
+fn message_report(command: &str, generated: &Generated, inputs: &MessageInputs<'_>, loaded: &LoadedConfig) -> MessageReport {
+    let tally = &generated.usage;
+    MessageReport {
+        schema_version: REPORT_SCHEMA_VERSION,
+        command: command.to_string(),
+        message: ReportMessage::new(&generated.message),
+        files: inputs.files.to_vec(),
+        stats: ReportStats::from(inputs.diff),
+        provider: loaded.config.provider.to_string(),
+        model: (generated.source == "model").then(|| loaded.config.default_model.clone()),
+        profile: loaded.profile.clone(),
+        message_source: generated.source.to_string(),
+        fallback_used: generated.source == "fallback",
+        fallback_reason: generated.fallback_reason.clone(),
+        tokens: ReportTokens::from(tally),
+        cost: tally.cost_known.then_some(tally.totals.cost),
+        requests: tally.totals.requests,
+        cache_hits: tally.cache_hits,
+        latency_ms: generated.latency.as_millis() as u64,
+        commit_oid: None,
+    }
+}
+
+fn print_usage(config: &rusty_commit::config::Config, by: Option<cli::UsageGrouping>) -> rusty_commit::errors::Result<()> {
+    let records = read_ledger()?;
+    if records.is_empty() {
+        println!("📭 No usage recorded yet.");
+        return Ok(());
+    }
+
+    let spent = month_to_date_cost(&records);
+    match config.budget.monthly_limit {
+        Some(limit) => println!("📈 This month: {:.4} of {:.2} budget ({})", spent, limit, config.budget.action),
+        None => println!("📈 This month: {:.4}", spent),
+    }
+
+    let print_totals = |title: &str, totals: Vec<(String, UsageTotals)>| {
+        println!("\n{}", title.bold());
+        for (key, total) in totals {
+            println!(
+                "  {:<40} {:>5} req {:>10} in {:>8} out {:>10.4}",
+                key, total.requests, total.prompt_tokens, total.completion_tokens, total.cost
+            );
+        }
+    };
+
+    if by.is_none() || by == Some(cli::UsageGrouping::Day) {
+        let by_day = totals_by(&records, |r| r.timestamp.with_timezone(&chrono::Local).format("%Y-%m-%d").to_string());
+        print_totals("By day", by_day.into_iter().collect());
+    }
+    if by.is_none() || by == Some(cli::UsageGrouping::Model) {
+        let by_model = totals_by(&records, |r| format!("{} ({})", r.model, r.provider));
+        print_totals("By model", by_model.into_iter().collect());
+    }
+    if by.is_none() || by == Some(cli::UsageGrouping::Repo) {
+        let by_repo = totals_by(&records, |r| r.repo.clone().unwrap_or_else(|| "-".to_string()));
+        print_totals("By repository", by_repo.into_iter().collect());
+    }
+
+    Ok(())
+}
+
+fn print_models(catalog: &ModelCatalog, models: &[&ModelInfo], config: &rusty_commit::config::Config) {
+    let age = catalog.age().as_secs() / 3600;
+    let source = match (catalog.stale, age) {
+        (true, _) => format!(" (provider unreachable, cached copy from {} h ago)", age).yellow().to_string(),
+        (false, 0) => String::new(),
+        (false, age) => format!(" (cached {} h ago, --refresh to update)", age).dimmed().to_string(),
+    };
+    println!("📚 {} of {} models from {}{}", models.len(), catalog.models.len(), config.provider, source);
+    if models.is_empty() {
+        return;
+    }
+
+    let width = models.iter().map(|model| model.id.len()).max().unwrap_or(0);
+    println!("\n  {:<width$}  {:>8}  {:>17}  CAPABILITIES", "MODEL", "CONTEXT", "$/1M IN / OUT", width = width + 2);
+    for model in models {
+        let marker = if model.id == config.default_model { "*" } else { " " };
+        let context = model
+            .context_length
+            .map(|context| format!("{}k", context / 1000))
+            .unwrap_or_else(|| "-".to_string());
+        let price = match model.price_per_million() {
+            _ if model.is_free() => "free".to_string(),
+            Some((prompt, completion)) => format!("{:.2} / {:.2}", prompt, completion),
+            None => "-".to_string(),
+        };
+        println!(
+            "  {} {:<width$}  {:>8}  {:>17}  {}",
+            marker,
+            model.id,
+            context,
+            price,
+            model.capabilities().join(", "),
+            width = width
+        );
+    }
+}
+
+/// Refuses models the provider doesn't list, suggesting the closest names.
+/// An unreachable provider with no cached list only gets a warning.
+async fn check_model(config: &rusty_commit::config::Config, model: &str) -> rusty_commit::errors::Result<()> {
+    let catalog = match load_models(&ApiSettings::from_config(config)?, false).await {
+        Ok(catalog) => catalog,
+        Err(e) => {
+            println!("⚠️  Could not check the model against the provider's list: {}", e);
+            return Ok(());
+        }
+    };
+    if catalog.get(model).is_some() {
+        return Ok(());
+    }
+
+    let ids: Vec<&str> = catalog.models.iter().map(|m| m.id.as_str()).collect();
+    let mut close = suggestions(model, ids.iter().copied(), 3);
+    if close.is_empty() {
+        close = fuzzy_filter(model, &ids, |id| id).into_iter().take(3).copied().collect();
+    }
+    let hint = match close.as_slice() {
+        [] => String::new(),
+        close => format!(", did you mean {}?", close.join(" or ")),
+    };
+    Err(CommitError::ConfigError(format!(
+        "{} doesn't serve a model called \"{}\"{} Use --skip-check to set it anyway",
+        config.provider,
+        model,
+        if hint.is_empty() { ".".to_string() } else { hint }
+    )))
+}
+
+async fn run_config_command(
+    command: &cli::ConfigCommands,
+    sources: &ConfigSources,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        cli::ConfigCommands::SetApiKey => {
+            let key = prompt_api_key()?;
+            save_api_key(sources, &key)?;
+        }
+        cli::ConfigCommands::SetModel { model, skip_check } => {
+            let (target, _) = scope_file(sources, &cli::ConfigScope { global: false, local: false })?;
+            // A broken config must not stop the model from being set
+            match load_config(sources) {
+                Ok(loaded) if !skip_check && loaded.config.provider != Provider::None => {
+                    check_model(&loaded.config, model).await?;
+                }
+                Err(e) if !skip_check => println!("⚠️  Could not check the model, the config doesn't load: {}", e),
+                _ => {}
+            }
+            set_config_value(&target, "default_model", model.clone().into())?;
+            println!("✅ Default model set to: {}", model);
+        }
+        cli::ConfigCommands::Get { key, scope } => {
+            check_key(key)?;
+            let value = if scope.global || scope.local {
+                let (path, _) = scope_file(sources, scope)?;
+                get_config_value(&path, key)?
+            } else {
+                load_config(sources)?.value(key).cloned()
+            };
+            match value {
+                Some(value) => println!("{}", display_value(key, &value)),
+                None => return Err(CommitError::ConfigError(format!("{} is not set", key)).into()),
+            }
+        }
+        cli::ConfigCommands::Set { key, value, scope } => {
+            let (path, origin) = scope_file(sources, scope)?;
+            let value = parse_setting(key, value)?;
+            if matches!(origin, ConfigOrigin::Repo(_)) {
+                check_repo_value(&path, key, &value)?;
+            }
+            let shown = display_value(key, &value);
+            set_config_value(&path, key, value)?;
+            match shown.as_str() {
+                HIDDEN => println!("✅ {} set in {}", key, path.display()),
+                shown => println!("✅ {} set to {} in {}", key, shown, path.display()),
+            }
+        }
+        cli::ConfigCommands::Unset { key, scope } => {
+            let (path, _) = scope_file(sources, scope)?;
+            // Unknown keys can still be removed, they may be what breaks the file
+            if unset_config_value(&path, key)? {
+                println!("✅ {} removed from {}", key, path.display());
+            } else {
+                check_key(key)?;
+                println!("ℹ️  {} is not set in {}", key, path.display());
+            }
+        }
+        cli::ConfigCommands::Edit { scope } => {
+            let (path, origin) = scope_file(sources, scope)?;
+            edit_config_file(&path, &origin)?;
+        }
+        cli::ConfigCommands::Validate => return validate(sources),
+        cli::ConfigCommands::Show { origin: true } => {
+            let loaded = load_config(sources)?;
+            warn_unused_keys(&loaded);
+            println!("📋 Current configuration{}:", profile_label(&loaded));
+            for (key, _) in CONFIG_KEYS {
+                if let (&"api_key", Some(origin)) = (key, &loaded.withheld_key) {
+                    println!("  api_key = Withheld {}", format!("({}, the repo config picks the endpoint)", origin).dimmed());
+                    continue;
+                }
+                let value = match loaded.value(key) {
+                    Some(value) => display_value(key, value),
+                    None => "Not set".to_string(),
+                };
+                println!("  {} = {} {}", key, value, format!("({})", loaded.origin(key)).dimmed());
+            }
+        }
+        cli::ConfigCommands::Show { origin: false } => {
+            let loaded = load_config(sources)?;
+            warn_unused_keys(&loaded);
+            let config = loaded.config.clone();
+            println!("📋 Current configuration{}:", profile_label(&loaded));
+            println!("  API Key: {}", match (&config.api_key, &loaded.withheld_key) {
+                (Some(_), _) => "Set",
+                (None, Some(_)) => "Withheld, the repo config picks the endpoint",
+                (None, None) => "Not set",
+            });
+            println!("  Default Model: {}", config.default_model);
+            println!("  Max Diff Lines: {}", config.max_diff_lines);
+            println!("  Temperature: {}", config.temperature);
+            println!("  Max Tokens: {}", config.max_tokens);
+            println!("  Commit Backend: {}", config.commit_backend);
+            println!("  Message Style: {}", config.message_style);
+            println!("  Provider: {}", config.provider);
+            println!("  Base URL: {}", config.base_url.as_deref().or(config.provider.default_base_url()).unwrap_or("-"));
+            println!("  Offline: {}", config.offline);
+            println!("  Cache: {}", if config.cache.enabled {
+                format!("on ({} h, {} MB)", config.cache.ttl_hours, config.cache.max_size_mb)
+            } else {
+                "off".to_string()
+            });
+            if !config.profile.is_empty() {
+                println!("  Profiles: {}", config.profile.keys().cloned().collect::<Vec<_>>().join(", "));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Keys the config holds but that won't be used, so a missing key isn't a mystery
+fn warn_unused_keys(loaded: &LoadedConfig) {
+    let config = &loaded.config;
+    if let Some(origin) = &loaded.withheld_key {
+        let base_url = config.base_url.as_deref().or(config.provider.default_base_url()).unwrap_or("-");
+        println!("{}", format!("⚠️  The repo config points requests at {}, not sending it the API key from {}", base_url, origin).yellow());
+    }
+    for key in &loaded.ignored_keys {
+        println!("{}", format!("⚠️  Ignoring {} in the repo config, a committed file may only hold keyring: or env:RUSTY_COMMIT_* references", key).yellow());
+    }
+}
+
+fn profile_label(loaded: &LoadedConfig) -> String {
+    match &loaded.profile {
+        Some(profile) => format!(" (profile {})", profile),
+        None => String::new(),
+    }
+}
+
+/// The file a scoped config command reads or writes, `--global` unless `--local` is given
+fn scope_file(sources: &ConfigSources, scope: &cli::ConfigScope) -> rusty_commit::errors::Result<(PathBuf, ConfigOrigin)> {
+    if scope.local {
+        let path = sources
+            .repo_path()
+            .ok_or_else(|| CommitError::ConfigError("--local needs to run inside a git repository".to_string()))?;
+        return Ok((path.clone(), ConfigOrigin::Repo(path)));
+    }
+    match &sources.explicit_path {
+        Some(path) => Ok((path.clone(), ConfigOrigin::Explicit(path.clone()))),
+        None => {
+            let path = sources
+                .global_path
+                .clone()
+                .ok_or_else(|| CommitError::ConfigError("no home directory".to_string()))?;
+            Ok((path.clone(), ConfigOrigin::Global(path)))
+        }
+    }
+}
+
+const HIDDEN: &str = "Set";
+
+/// Never prints a plain API key, only references to where it's kept
+fn display_value(key: &str, value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(value) if SECRET_KEYS.contains(&key) && !is_secret_ref(value) => HIDDEN.to_string(),
+        toml::Value::String(value) => value.clone(),
+        // Settings are f32, don't show the f64 rounding noise
+        toml::Value::Float(value) => (*value as f32).to_string(),
+        value => value.to_string(),
+    }
+}
+
+/// Opens the file in the user's editor until it passes validation or they give up
+fn edit_config_file(path: &Path, origin: &ConfigOrigin) -> Result<(), Box<dyn std::error::Error>> {
+    require_input("an editor session, use config set instead")?;
+    if !path.exists() {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, format!("# rusty-commit configuration\nversion = {}\n", CONFIG_VERSION))?;
+    }
+
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+
+    loop {
+        // Through the shell, so editors given with arguments like "code --wait" work
+        let status = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("{} \"$@\"", editor))
+            .arg("editor")
+            .arg(path)
+            .status()?;
+        if !status.success() {
+            return Err(CommitError::ConfigError(format!("{} exited with {}", editor, status)).into());
+        }
+
+        match read_config_file(path, origin) {
+            Ok(_) => {
+                println!("✅ {} is valid", path.display());
+                return Ok(());
+            }
+            Err(e) if confirm_retry(&e.to_string(), "Edit the file again?")? => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// The `init` wizard: provider, key, model, style and hook, written to global or repo config
+async fn run_init(sources: &ConfigSources, repo: Option<&git2::Repository>) -> Result<(), Box<dyn std::error::Error>> {
+    println!("👋 Let's set up rusty-commit");
+    
+    // Start from what's configured so re-running only changes what the user changes
+    let mut config = load_config(sources).map(|loaded| loaded.config).unwrap_or_default();
+    let previous_provider = config.provider;
+    config.provider = select_provider(config.provider)?;
+    
+    let global = scope_file(sources, &cli::ConfigScope { global: true, local: false })?;
+    let (path, origin) = match sources.repo_path() {
+        Some(repo_path) if select_config_target(&global.0, &repo_path)? => (repo_path.clone(), ConfigOrigin::Repo(repo_path)),
+        _ => global,
+    };
+    
+    let mut new_key = None;
+    match config.provider {
+        Provider::OpenRouter => loop {
+            let key = prompt_api_key()?;
+            config.api_key = Some(key.clone());
+            println!("🔌 Checking the key...");
+            match check_api_key(&ApiSettings::from_config(&config)?).await {
+                Ok(()) => {
+                    println!("✅ Key works");
+                    new_key = Some(key);
+                    break;
+                }
+                Err(e) if confirm_retry(&e.to_string(), "Enter the key again?")? => continue,
+                Err(e) => return Err(e.into()),
+            }
+        },
+        Provider::Local => loop {
+            let current = config.base_url.clone().or(Provider::Local.default_base_url().map(str::to_string)).unwrap_or_default();
+            config.base_url = Some(prompt_base_url(&current)?);
+            println!("🔌 Checking the server...");
+            match check_api_key(&ApiSettings::from_config(&config)?).await {
+                Ok(()) => {
+                    println!("✅ Server is up");
+                    break;
+                }
+                // It may simply not be running right now
+                Err(e) if confirm_retry(&e.to_string(), "Try again? (no keeps the URL anyway)")? => continue,
+                Err(_) => break,
+            }
+        },
+        Provider::None => {}
+    }
+    
+    if config.provider != Provider::None {
+        let models = match load_models(&ApiSettings::from_config(&config)?, true).await {
+            Ok(catalog) => catalog.models,
+            Err(e) => {
+                println!("⚠️  Could not fetch the model list: {}", e);
+                Vec::new()
+            }
+        };
+        config.default_model = select_model(&models, &config.default_model)?;
+    }
+    config.message_style = select_message_style(config.message_style)?;
+    
+    // Keys go to the keyring or the global file, never to a file that might get committed
+    if let Some(key) = &new_key {
+        save_api_key(sources, key)?;
+    }
+    set_config_value(&path, "provider", config.provider.to_string().into())?;
+    // A URL only makes sense for the provider it was set for
+    match (&config.base_url, config.provider) {
+        (Some(base_url), Provider::Local) => set_config_value(&path, "base_url", base_url.clone().into())?,
+        _ if config.provider != previous_provider => {
+            unset_config_value(&path, "base_url")?;
+        }
+        _ => {}
+    }
+    if config.provider != Provider::None {
+        set_config_value(&path, "default_model", config.default_model.clone().into())?;
+    }
+    set_config_value(&path, "message_style", config.message_style.to_string().into())?;
+    read_config_file(&path, &origin)?;
+    println!("✅ Settings saved to {}", path.display());
+    
+    if let Some(repo) = repo {
+        if confirm_install_hook()? {
+            println!("🪝 Installed {}", install_hook(repo)?.display());
+        }
+    }
+    
+    println!("🎉 All set! Stage some changes and run `rusty-commit commit`");
+    Ok(())
+}
+
+/// Keeps the key in the OS keyring and only a reference to it in config
+fn save_api_key(sources: &ConfigSources, key: &str) -> rusty_commit::errors::Result<()> {
+    let path = sources
+        .writable_path()
+        .ok_or_else(|| CommitError::ConfigError("no home directory".to_string()))?;
+
+    match store_in_keyring(DEFAULT_KEYRING_ACCOUNT, key) {
+        Ok(reference) => {
+            set_config_value(&path, "api_key", reference.into())?;
+            println!("✅ API key saved to the system keyring");
+        }
+        Err(e) => {
+            println!("⚠️  {}. Saving the key in plain text to {}", e, path.display());
+            set_config_value(&path, "api_key", key.into())?;
+            println!("✅ API key saved to config");
+        }
+    }
+    Ok(())
+}
+
+fn validate(sources: &ConfigSources) -> Result<(), Box<dyn std::error::Error>> {
+    let mut valid = true;
+    for (source, result) in validate_config(sources) {
+        match result {
+            Ok(()) => println!("✅ {}", source),
+            Err(e) => {
+                valid = false;
+                println!("❌ {}\n{}", source, e);
+            }
+        }
+    }
+
+    if !valid {
+        return Err(CommitError::ConfigError("configuration is invalid".to_string()).into());
+    }
+    println!("🎉 Configuration is valid");
+    Ok(())
+}
@@ -0,0 +1,251 @@
+use crate::utils::diff::{DiffSummary, HunkDiff};
+use crate::utils::exclude::{is_lockfile, is_minified_path, is_vendored_path};
+
+// Room for the instructions and file list around the diff
+const PROMPT_OVERHEAD_TOKENS: usize = 500;
+
+// Room for the one "... truncated, omitted" line of a file that doesn't fit whole
+const NOTE_TOKENS: usize = 24;
+
+/// Diffs this many times over the budget get summarized in parts, anything
+/// smaller is cut down to fit by `budget_diff`
+pub const MAP_REDUCE_FACTOR: usize = 4;
+
+// Context windows by model id fragment, more specific fragments first
+const CONTEXT_WINDOWS: &[(&str, usize)] = &[
+    ("gpt-4o", 128_000),
+    ("gpt-4.1", 1_000_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4", 8_192),
+    ("gpt-3.5-turbo", 16_385),
+    ("claude", 200_000),
+    ("gemini", 1_000_000),
+    ("llama-3.1", 128_000),
+    ("llama-3", 8_192),
+    ("mistral", 32_000),
+    ("mixtral", 32_000),
+    ("deepseek", 64_000),
+    ("qwen", 32_000),
+];
+
+const DEFAULT_CONTEXT_WINDOW: usize = 8_192;
+
+#[derive(Debug, Clone, Copy)]
+pub struct DiffBudget {
+    pub max_tokens: usize,
+    pub max_lines: usize,
+    chars_per_token: f32,
+}
+
+impl DiffBudget {
+    /// Whatever the model's context leaves after the completion and prompt text
+    pub fn for_model(model: &str, completion_tokens: u32, max_lines: usize) -> Self {
+        let max_tokens = context_window(model)
+            .saturating_sub(completion_tokens as usize + PROMPT_OVERHEAD_TOKENS);
+
+        Self {
+            max_tokens,
+            max_lines,
+            chars_per_token: chars_per_token(model),
+        }
+    }
+
+    pub fn estimate_tokens(&self, text: &str) -> usize {
+        (text.chars().count() as f32 / self.chars_per_token).ceil() as usize
+    }
+}
+
+/// Splits a diff that doesn't fit the budget into parts that each do, keeping
+/// files whole and in order. A file bigger than the budget gets a part of its own.
+pub fn split_into_chunks(summary: &DiffSummary, budget: &DiffBudget) -> Vec<DiffSummary> {
+    let mut chunks = Vec::new();
+    let mut current = Vec::new();
+    let mut current_tokens = 0;
+
+    for file in &summary.files {
+        let tokens = budget.estimate_tokens(&DiffSummary::from_files(vec![file.clone()]).to_prompt_text());
+        if !current.is_empty() && current_tokens + tokens > budget.max_tokens {
+            chunks.push(DiffSummary::from_files(std::mem::take(&mut current)));
+            current_tokens = 0;
+        }
+        current.push(file.clone());
+        current_tokens += tokens;
+    }
+
+    if !current.is_empty() {
+        chunks.push(DiffSummary::from_files(current));
+    }
+
+    chunks
+}
+
+pub fn context_window(model: &str) -> usize {
+    let model = model.to_lowercase();
+    CONTEXT_WINDOWS
+        .iter()
+        .find(|(fragment, _)| model.contains(fragment))
+        .map(|(_, window)| *window)
+        .unwrap_or(DEFAULT_CONTEXT_WINDOW)
+}
+
+fn chars_per_token(model: &str) -> f32 {
+    // Claude's tokenizer splits code a little finer than OpenAI's
+    if model.to_lowercase().contains("claude") {
+        3.5
+    } else {
+        4.0
+    }
+}
+
+/// Lockfiles, minified assets, build output and vendored code
+pub fn is_generated_path(path: &str) -> bool {
+    const GENERATED_MARKERS: &[&str] = &[".js.map", ".generated.", ".pb.go", "_pb2.py", ".snap"];
+    const BUILD_DIRS: &[&str] = &["dist", "build", "target"];
+
+    let lower = path.to_lowercase();
+    let file_name = lower.rsplit('/').next().unwrap_or(&lower);
+
+    is_lockfile(path)
+        || is_minified_path(path)
+        || is_vendored_path(path)
+        || GENERATED_MARKERS.iter().any(|marker| file_name.contains(marker))
+        || lower.split('/').rev().skip(1).any(|dir| BUILD_DIRS.contains(&dir))
+}
+
+/// Renders the diff for the prompt within the budget. Every file keeps its
+/// header line; hunks are handed out per file, generated files last, and whatever
+/// doesn't fit is replaced by a one-line summary of what was dropped. Only the
+/// headers and those summaries can push it over a budget too small for them.
+pub fn budget_diff(summary: &DiffSummary, budget: &DiffBudget) -> String {
+    let headers: Vec<String> = summary.files.iter().map(|f| format!("{}\n", f.prompt_header())).collect();
+    let header_tokens: usize = headers.iter().map(|h| budget.estimate_tokens(h)).sum();
+    let notes = summary.files.iter().filter(|f| f.excluded.is_none() && !f.hunks.is_empty()).count();
+
+    let tokens_left = budget.max_tokens.saturating_sub(header_tokens + notes * NOTE_TOKENS);
+    let lines_left = budget.max_lines.saturating_sub(headers.len() + notes);
+
+    let hunk_texts: Vec<Vec<String>> = summary
+        .files
+        .iter()
+        .map(|file| match file.excluded {
+            Some(_) => Vec::new(),
+            None => file.hunks.iter().map(HunkDiff::to_text).collect(),
+        })
+        .collect();
+    let token_costs: Vec<usize> = hunk_texts
+        .iter()
+        .map(|texts| texts.iter().map(|t| budget.estimate_tokens(t)).sum())
+        .collect();
+    let line_costs: Vec<usize> = hunk_texts
+        .iter()
+        .map(|texts| texts.iter().map(|t| t.lines().count()).sum())
+        .collect();
+
+    let generated: Vec<bool> = summary.files.iter().map(|f| is_generated_path(&f.path)).collect();
+    let token_allocation = allocate(&generated, &token_costs, tokens_left);
+    let line_allocation = allocate(&generated, &line_costs, lines_left);
+
+    let mut out = String::new();
+    for (i, file) in summary.files.iter().enumerate() {
+        out.push_str(&headers[i]);
+
+        let mut tokens_left = token_allocation[i];
+        let mut lines_left = line_allocation[i];
+        let mut included_any = false;
+        let mut truncated = None;
+        let mut omitted = (0, 0, 0);
+
+        // Excluded files have no texts, their header already says it all
+        for (hunk, text) in file.hunks.iter().zip(&hunk_texts[i]) {
+            let tokens = budget.estimate_tokens(text);
+            let lines = text.lines().count();
+
+            if tokens <= tokens_left && lines <= lines_left {
+                out.push_str(text);
+                tokens_left -= tokens;
+                lines_left -= lines;
+                included_any = true;
+            } else if !included_any && tokens_left > 0 && lines_left > 0 {
+                // One huge hunk, e.g. a new file: show its beginning
+                let (partial, shown) = truncate_lines(text, tokens_left, lines_left, budget);
+                out.push_str(&partial);
+                truncated = Some((shown, lines));
+                tokens_left = 0;
+                lines_left = 0;
+                included_any = true;
+            } else {
+                let (additions, deletions) = hunk_stats(hunk);
+                omitted = (omitted.0 + 1, omitted.1 + additions, omitted.2 + deletions);
+            }
+        }
+
+        let mut note = Vec::new();
+        if let Some((shown, lines)) = truncated {
+            note.push(format!("hunk truncated ({} of {} lines shown)", shown, lines));
+        }
+        if omitted.0 > 0 {
+            note.push(format!(
+                "{} hunk{} omitted (+{} -{})",
+                omitted.0,
+                if omitted.0 == 1 { "" } else { "s" },
+                omitted.1,
+                omitted.2
+            ));
+        }
+        if !note.is_empty() {
+            out.push_str(&format!("... {}\n", note.join(", ")));
+        }
+    }
+
+    out
+}
+
+/// Water-filling: small files get everything they need, big ones split what
+/// remains, and generated files only get what source files left over.
+/// Never hands out more than `available` in total.
+pub fn allocate(generated: &[bool], costs: &[usize], mut available: usize) -> Vec<usize> {
+    let mut allocation = vec![0; costs.len()];
+
+    for tier_generated in [false, true] {
+        let mut tier: Vec<usize> = (0..costs.len()).filter(|&i| generated[i] == tier_generated).collect();
+        tier.sort_by_key(|&i| costs[i]);
+
+        let mut remaining = tier.len();
+        for i in tier {
+            let grant = costs[i].min(available / remaining);
+            allocation[i] = grant;
+            available -= grant;
+            remaining -= 1;
+        }
+    }
+
+    allocation
+}
+
+/// The leading whole lines of `text` that fit, and how many that is
+pub fn truncate_lines(text: &str, max_tokens: usize, max_lines: usize, budget: &DiffBudget) -> (String, usize) {
+    let mut out = String::new();
+    let mut used = 0;
+    let mut count = 0;
+
+    for line in text.lines().take(max_lines) {
+        let tokens = budget.estimate_tokens(line) + 1;
+        if used + tokens > max_tokens {
+            break;
+        }
+        out.push_str(line);
+        out.push('\n');
+        used += tokens;
+        count += 1;
+    }
+
+    (out, count)
+}
+
+fn hunk_stats(hunk: &HunkDiff) -> (usize, usize) {
+    hunk.lines.iter().fold((0, 0), |(additions, deletions), line| match line.origin {
+        '+' => (additions + 1, deletions),
+        '-' => (additions, deletions + 1),
+        _ => (additions, deletions),
+    })
+}
@@ -0,0 +1,373 @@
+use git2::{Delta, Diff, DiffDelta, DiffFile, Patch};
+use serde::Serialize;
+use crate::errors::Result;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DiffSummary {
+    pub files: Vec<FileDiff>,
+    pub stats: DiffStats,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct DiffStats {
+    pub files_changed: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileDiff {
+    pub path: String,
+    /// Set when the file lived somewhere else before the change
+    pub old_path: Option<String>,
+    pub status: FileStatus,
+    /// Git file modes, 0 when the file is absent on that side
+    pub old_mode: u32,
+    pub new_mode: u32,
+    pub binary: bool,
+    pub old_size: u64,
+    pub new_size: u64,
+    /// Blob ids, `None` when that side doesn't exist
+    pub old_id: Option<String>,
+    pub new_id: Option<String>,
+    /// One-line stand-in for the content of lockfiles, generated and binary files
+    pub excluded: Option<String>,
+    pub additions: usize,
+    pub deletions: usize,
+    pub hunks: Vec<HunkDiff>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileStatus {
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+    Copied,
+    Typechange,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HunkDiff {
+    pub header: String,
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffLine {
+    /// `+`, `-` or ` `, or one of git's `=`, `>`, `<` end-of-file markers
+    pub origin: char,
+    pub content: String,
+}
+
+impl DiffSummary {
+    pub fn from_diff(diff: &Diff) -> Result<Self> {
+        let mut summary = DiffSummary::default();
+
+        for (idx, delta) in diff.deltas().enumerate() {
+            let file = file_diff(diff, idx, &delta)?;
+            summary.stats.files_changed += 1;
+            summary.stats.insertions += file.additions;
+            summary.stats.deletions += file.deletions;
+            summary.files.push(file);
+        }
+
+        Ok(summary)
+    }
+
+    pub fn from_files(files: Vec<FileDiff>) -> Self {
+        let stats = DiffStats {
+            files_changed: files.len(),
+            insertions: files.iter().map(|f| f.additions).sum(),
+            deletions: files.iter().map(|f| f.deletions).sum(),
+        };
+        Self { files, stats }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    pub fn paths(&self) -> Vec<String> {
+        self.files.iter().map(|f| f.path.clone()).collect()
+    }
+
+    /// Compact rendering for the model: one header line per file, then the hunks
+    pub fn to_prompt_text(&self) -> String {
+        let mut out = String::new();
+
+        for file in &self.files {
+            out.push_str(&file.prompt_header());
+            out.push('\n');
+            if file.excluded.is_some() {
+                continue;
+            }
+            for hunk in &file.hunks {
+                hunk.render_into(&mut out);
+            }
+        }
+
+        out
+    }
+
+    /// A unified patch `git apply` accepts. Binary files carry only their
+    /// blob ids, so applying them needs the new blobs in the object store.
+    pub fn to_patch(&self) -> String {
+        let mut out = String::new();
+
+        for file in &self.files {
+            let old_path = file.old_path.as_deref().unwrap_or(&file.path);
+            out.push_str(&format!("diff --git a/{} b/{}\n", old_path, file.path));
+
+            match file.status {
+                FileStatus::Added => out.push_str(&format!("new file mode {:06o}\n", file.new_mode)),
+                FileStatus::Deleted => out.push_str(&format!("deleted file mode {:06o}\n", file.old_mode)),
+                _ => {
+                    if file.old_mode != file.new_mode {
+                        out.push_str(&format!("old mode {:06o}\nnew mode {:06o}\n", file.old_mode, file.new_mode));
+                    }
+                    if file.status == FileStatus::Renamed {
+                        out.push_str(&format!("rename from {}\nrename to {}\n", old_path, file.path));
+                    }
+                    if file.status == FileStatus::Copied {
+                        out.push_str(&format!("copy from {}\ncopy to {}\n", old_path, file.path));
+                    }
+                }
+            }
+
+            // Unchanged content after a rename, copy or mode change has no index line
+            if file.old_id != file.new_id {
+                out.push_str(&format!("index {}..{}", patch_id(&file.old_id), patch_id(&file.new_id)));
+                if file.old_mode == file.new_mode {
+                    out.push_str(&format!(" {:06o}", file.new_mode));
+                }
+                out.push('\n');
+            }
+
+            let old_name = match file.status {
+                FileStatus::Added => "/dev/null".to_string(),
+                _ => format!("a/{}", old_path),
+            };
+            let new_name = match file.status {
+                FileStatus::Deleted => "/dev/null".to_string(),
+                _ => format!("b/{}", file.path),
+            };
+
+            if file.binary {
+                out.push_str(&format!("Binary files {} and {} differ\n", old_name, new_name));
+                continue;
+            }
+            if file.hunks.is_empty() {
+                continue;
+            }
+
+            out.push_str(&format!("--- {}\n+++ {}\n", old_name, new_name));
+            for hunk in &file.hunks {
+                hunk.render_into(&mut out);
+            }
+        }
+
+        out
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+impl FileDiff {
+    pub fn prompt_header(&self) -> String {
+        let mut header = match &self.old_path {
+            Some(old_path) => format!("{} -> {}", old_path, self.path),
+            None => self.path.clone(),
+        };
+
+        let mut notes = vec![self.status.to_string()];
+        notes.extend(self.mode_change());
+
+        if let Some(excluded) = &self.excluded {
+            // "Cargo.lock: 3 packages updated" unless it also moved or changed mode
+            if notes.len() > 1 || self.status != FileStatus::Modified {
+                header.push_str(&format!(" ({})", notes.join(", ")));
+            }
+            return format!("{}: {}", header, excluded);
+        }
+
+        header.push_str(&format!(" ({}", notes.join(", ")));
+        if self.binary {
+            header.push_str(", binary");
+        } else {
+            header.push_str(&format!(", +{} -{}", self.additions, self.deletions));
+        }
+        header.push(')');
+
+        header
+    }
+
+    /// Describes permission and file type changes, e.g. "made executable"
+    pub fn mode_change(&self) -> Option<String> {
+        if self.old_mode == 0 || self.new_mode == 0 || self.old_mode == self.new_mode {
+            return None;
+        }
+
+        if self.status == FileStatus::Typechange {
+            return Some(format!("{} -> {}", mode_kind(self.old_mode), mode_kind(self.new_mode)));
+        }
+
+        Some(match (self.old_mode, self.new_mode) {
+            (MODE_FILE, MODE_EXECUTABLE) => "made executable".to_string(),
+            (MODE_EXECUTABLE, MODE_FILE) => "no longer executable".to_string(),
+            (old, new) => format!("mode {:06o} -> {:06o}", old, new),
+        })
+    }
+}
+
+const MODE_FILE: u32 = 0o100644;
+const MODE_EXECUTABLE: u32 = 0o100755;
+const MODE_SYMLINK: u32 = 0o120000;
+const MODE_SUBMODULE: u32 = 0o160000;
+
+fn mode_kind(mode: u32) -> &'static str {
+    match mode {
+        MODE_SYMLINK => "symlink",
+        MODE_SUBMODULE => "submodule",
+        MODE_EXECUTABLE => "executable file",
+        _ => "file",
+    }
+}
+
+impl HunkDiff {
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        self.render_into(&mut out);
+        out
+    }
+
+    fn render_into(&self, out: &mut String) {
+        out.push_str(&self.header);
+        out.push('\n');
+
+        for line in &self.lines {
+            match line.origin {
+                '+' | '-' | ' ' => {
+                    out.push(line.origin);
+                    out.push_str(&line.content);
+                    if !line.content.ends_with('\n') {
+                        out.push('\n');
+                    }
+                }
+                _ => out.push_str("\\ No newline at end of file\n"),
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for FileStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            FileStatus::Added => "added",
+            FileStatus::Modified => "modified",
+            FileStatus::Deleted => "deleted",
+            FileStatus::Renamed => "renamed",
+            FileStatus::Copied => "copied",
+            FileStatus::Typechange => "type changed",
+        };
+        write!(f, "{}", status)
+    }
+}
+
+fn file_diff(diff: &Diff, idx: usize, delta: &DiffDelta) -> Result<FileDiff> {
+    let path = delta
+        .new_file()
+        .path()
+        .or_else(|| delta.old_file().path())
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let old_path = delta
+        .old_file()
+        .path()
+        .map(|p| p.to_string_lossy().to_string())
+        .filter(|old| *old != path);
+
+    let status = match delta.status() {
+        Delta::Added | Delta::Untracked => FileStatus::Added,
+        Delta::Deleted => FileStatus::Deleted,
+        Delta::Renamed => FileStatus::Renamed,
+        Delta::Copied => FileStatus::Copied,
+        Delta::Typechange => FileStatus::Typechange,
+        _ => FileStatus::Modified,
+    };
+
+    let mut file = FileDiff {
+        path,
+        old_path,
+        status,
+        old_mode: i32::from(delta.old_file().mode()) as u32,
+        new_mode: i32::from(delta.new_file().mode()) as u32,
+        binary: delta.flags().is_binary(),
+        old_size: delta.old_file().size(),
+        new_size: delta.new_file().size(),
+        old_id: blob_id(&delta.old_file()),
+        new_id: blob_id(&delta.new_file()),
+        excluded: None,
+        additions: 0,
+        deletions: 0,
+        hunks: Vec::new(),
+    };
+
+    // No patch means git decided the content is binary
+    let patch = match Patch::from_diff(diff, idx)? {
+        Some(patch) => patch,
+        None => {
+            file.binary = true;
+            return Ok(file);
+        }
+    };
+    file.binary |= patch.delta().flags().is_binary();
+    // Sizes are only known once the patch has loaded the content
+    file.old_size = patch.delta().old_file().size();
+    file.new_size = patch.delta().new_file().size();
+
+    for hunk_idx in 0..patch.num_hunks() {
+        let (hunk, line_count) = patch.hunk(hunk_idx)?;
+        let mut lines = Vec::with_capacity(line_count);
+
+        for line_idx in 0..line_count {
+            let line = patch.line_in_hunk(hunk_idx, line_idx)?;
+            match line.origin() {
+                '+' => file.additions += 1,
+                '-' => file.deletions += 1,
+                _ => {}
+            }
+            lines.push(DiffLine {
+                origin: line.origin(),
+                content: String::from_utf8_lossy(line.content()).to_string(),
+            });
+        }
+
+        file.hunks.push(HunkDiff {
+            header: String::from_utf8_lossy(hunk.header()).trim_end().to_string(),
+            old_start: hunk.old_start(),
+            old_lines: hunk.old_lines(),
+            new_start: hunk.new_start(),
+            new_lines: hunk.new_lines(),
+            lines,
+        });
+    }
+
+    Ok(file)
+}
+
+/// Full ids so `git apply` can find binary blobs, zeros for a missing side
+fn patch_id(id: &Option<String>) -> &str {
+    id.as_deref().unwrap_or("0000000000000000000000000000000000000000")
+}
+
+fn blob_id(file: &DiffFile) -> Option<String> {
+    Some(file.id()).filter(|id| !id.is_zero()).map(|id| id.to_string())
+}
@@ -34,7 +34,6 @@ fn save_env_variable(shell: &str, key: &str, value: &str) -> Result<()> {
 
     let mut config_file = std::fs::OpenOptions::new()
         .read(true)
-        .write(true)
         .append(true)
         .open(config_file_path)?;
 
@@ -0,0 +1,278 @@
+use git2::{AttrCheckFlags, AttrValue, Repository};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use crate::errors::Result;
+use crate::utils::diff::{DiffSummary, FileDiff};
+
+const LOCKFILES: &[&str] = &[
+    "cargo.lock",
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "poetry.lock",
+    "pipfile.lock",
+    "gemfile.lock",
+    "composer.lock",
+    "go.sum",
+];
+const MINIFIED_MARKERS: &[&str] = &[".min.js", ".min.mjs", ".min.css"];
+const VENDOR_DIRS: &[&str] = &["vendor", "third_party", "node_modules"];
+
+// Lines this long only come out of bundlers and minifiers
+const MINIFIED_LINE_LENGTH: usize = 500;
+
+pub fn is_lockfile(path: &str) -> bool {
+    LOCKFILES.contains(&file_name(path).as_str())
+}
+
+pub fn is_minified_path(path: &str) -> bool {
+    let name = file_name(path);
+    MINIFIED_MARKERS.iter().any(|marker| name.ends_with(marker))
+}
+
+pub fn is_vendored_path(path: &str) -> bool {
+    path.to_lowercase().split('/').rev().skip(1).any(|dir| VENDOR_DIRS.contains(&dir))
+}
+
+/// Swaps the content of lockfiles, minified, vendored, generated and binary
+/// files for a one-line summary so they don't crowd out real changes
+pub fn exclude_noise(repo: &Repository, summary: &mut DiffSummary) -> Result<()> {
+    for file in &mut summary.files {
+        file.excluded = exclusion_summary(repo, file)?;
+    }
+    Ok(())
+}
+
+fn exclusion_summary(repo: &Repository, file: &FileDiff) -> Result<Option<String>> {
+    if file.binary {
+        return Ok(Some(format!(
+            "binary file, {} -> {}",
+            human_size(file.old_size),
+            human_size(file.new_size)
+        )));
+    }
+    if is_lockfile(&file.path) {
+        return Ok(Some(lockfile_summary(file)));
+    }
+    if is_minified_path(&file.path) || has_minified_lines(file) {
+        return Ok(Some(format!("minified asset, {}", line_stats(file))));
+    }
+    if is_vendored_path(&file.path) {
+        return Ok(Some(format!("vendored code, {}", line_stats(file))));
+    }
+    if marked_generated(repo, &file.path)? {
+        return Ok(Some(format!("generated file, {}", line_stats(file))));
+    }
+
+    Ok(None)
+}
+
+/// `linguist-generated`, `linguist-vendored` or `-diff` in .gitattributes
+fn marked_generated(repo: &Repository, path: &str) -> Result<bool> {
+    let path = Path::new(path);
+    let flags = AttrCheckFlags::FILE_THEN_INDEX;
+
+    let is_set = |value: AttrValue| matches!(value, AttrValue::True | AttrValue::String("true"));
+    let generated = AttrValue::from_string(repo.get_attr(path, "linguist-generated", flags)?);
+    let vendored = AttrValue::from_string(repo.get_attr(path, "linguist-vendored", flags)?);
+    let diff = AttrValue::from_string(repo.get_attr(path, "diff", flags)?);
+
+    Ok(is_set(generated) || is_set(vendored) || matches!(diff, AttrValue::False))
+}
+
+/// What one lockfile line says about a package
+enum LockLine {
+    Name(String),
+    Version(String),
+    /// Formats that put both on one line, like go.sum or Gemfile.lock
+    Both(String, String),
+}
+
+// Objects in JSON lockfiles that hold packages rather than being one
+const JSON_CONTAINERS: &[&str] = &[
+    "",
+    "packages",
+    "dependencies",
+    "devDependencies",
+    "peerDependencies",
+    "optionalDependencies",
+    "requires",
+    "engines",
+    "bin",
+    "funding",
+    "default",
+    "develop",
+    "_meta",
+];
+
+fn parse_lock_line(file_name: &str, line: &str) -> Option<LockLine> {
+    let trimmed = line.trim();
+    let unquote = |value: &str| value.trim().trim_end_matches(',').trim_matches(|c| c == '"' || c == '\'').to_string();
+
+    match file_name {
+        "go.sum" => {
+            // Every module version has a second line for its go.mod
+            let mut fields = trimmed.split_whitespace();
+            let (module, version) = (fields.next()?, fields.next()?);
+            (!version.ends_with("/go.mod")).then(|| LockLine::Both(module.to_string(), version.to_string()))
+        }
+        "gemfile.lock" => {
+            // "    rake (13.0.6)", deeper lines are dependency requirements
+            let spec = line.strip_prefix("    ").filter(|spec| !spec.starts_with(' '))?;
+            let (name, version) = spec.trim_end().strip_suffix(')')?.split_once(" (")?;
+            Some(LockLine::Both(name.to_string(), version.to_string()))
+        }
+        "pnpm-lock.yaml" => {
+            // "  /foo@1.0.0:" or "  '@scope/foo@1.0.0(peer@2.0.0)':"
+            let key = trimmed.strip_suffix(':')?.trim_matches('\'').trim_start_matches('/');
+            let key = key.split('(').next()?;
+            let at = key.rfind('@').filter(|&at| at > 0)?;
+            Some(LockLine::Both(key[..at].to_string(), key[at + 1..].to_string()))
+        }
+        "yarn.lock" => {
+            if let Some(version) = trimmed.strip_prefix("version ").or_else(|| trimmed.strip_prefix("version: ")) {
+                return Some(LockLine::Version(unquote(version)));
+            }
+            // Unindented `"foo@^1.0.0", foo@^1.1.0:` starts a package
+            if line.starts_with(' ') || line.starts_with('#') || !trimmed.ends_with(':') {
+                return None;
+            }
+            let spec = unquote(trimmed.split(',').next()?.trim_end_matches(':'));
+            let at = spec.rfind('@').filter(|&at| at > 0)?;
+            Some(LockLine::Name(spec[..at].to_string()))
+        }
+        "package-lock.json" | "npm-shrinkwrap.json" | "pipfile.lock" | "composer.lock" => {
+            if let Some(version) = trimmed.strip_prefix("\"version\":") {
+                return Some(LockLine::Version(unquote(version)));
+            }
+            if let Some(name) = trimmed.strip_prefix("\"name\":") {
+                return Some(LockLine::Name(unquote(name)));
+            }
+            // `"node_modules/foo": {` opens a package
+            let key = trimmed.strip_suffix('{')?.trim_end().strip_suffix(':')?;
+            let key = unquote(key);
+            (!JSON_CONTAINERS.contains(&key.as_str())).then(|| LockLine::Name(key.trim_start_matches("node_modules/").to_string()))
+        }
+        // Cargo.lock and poetry.lock
+        _ => {
+            if let Some(name) = trimmed.strip_prefix("name = ") {
+                Some(LockLine::Name(unquote(name)))
+            } else {
+                trimmed.strip_prefix("version = ").map(|version| LockLine::Version(unquote(version)))
+            }
+        }
+    }
+}
+
+/// Counts packages by comparing what each side of the diff lists. Context lines
+/// name the package whose version line changed.
+fn lockfile_summary(file: &FileDiff) -> String {
+    let file_name = file_name(&file.path);
+    let mut old_names = BTreeSet::new();
+    let mut new_names = BTreeSet::new();
+    let mut old_versions = BTreeMap::new();
+    let mut new_versions = BTreeMap::new();
+
+    for hunk in &file.hunks {
+        // The package each side is in, a hunk may start in the middle of one
+        let mut old_current: Option<String> = None;
+        let mut new_current: Option<String> = None;
+
+        for line in &hunk.lines {
+            let (in_old, in_new) = match line.origin {
+                ' ' => (true, true),
+                '-' => (true, false),
+                '+' => (false, true),
+                _ => continue,
+            };
+            let Some(parsed) = parse_lock_line(&file_name, line.content.trim_end_matches('\n')) else {
+                continue;
+            };
+
+            let (name, version) = match parsed {
+                LockLine::Name(name) => (Some(name), None),
+                LockLine::Version(version) => (None, Some(version)),
+                LockLine::Both(name, version) => (Some(name), Some(version)),
+            };
+            if let Some(name) = name {
+                if in_old {
+                    old_current = Some(name.clone());
+                }
+                if in_new {
+                    new_current = Some(name.clone());
+                }
+                match line.origin {
+                    '-' => {
+                        old_names.insert(name);
+                    }
+                    '+' => {
+                        new_names.insert(name);
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(version) = version {
+                match (line.origin, &old_current, &new_current) {
+                    ('-', Some(name), _) => {
+                        old_versions.insert(name.clone(), version);
+                    }
+                    ('+', _, Some(name)) => {
+                        new_versions.insert(name.clone(), version);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    let added = new_names.difference(&old_names).count();
+    let removed = old_names.difference(&new_names).count();
+    let updated = new_versions
+        .iter()
+        .filter(|(name, version)| {
+            old_versions.get(*name).is_some_and(|old| old != *version)
+                && !(new_names.contains(*name) ^ old_names.contains(*name))
+        })
+        .count();
+
+    let mut parts = Vec::new();
+    if updated > 0 {
+        parts.push(format!("{} package{} updated", updated, if updated == 1 { "" } else { "s" }));
+    }
+    if added > 0 {
+        parts.push(format!("{} added", added));
+    }
+    if removed > 0 {
+        parts.push(format!("{} removed", removed));
+    }
+
+    if parts.is_empty() {
+        format!("lockfile, {}", line_stats(file))
+    } else {
+        parts.join(", ")
+    }
+}
+
+fn has_minified_lines(file: &FileDiff) -> bool {
+    file.hunks
+        .iter()
+        .flat_map(|h| &h.lines)
+        .any(|line| line.origin == '+' && line.content.len() > MINIFIED_LINE_LENGTH)
+}
+
+fn line_stats(file: &FileDiff) -> String {
+    format!("+{} -{} lines", file.additions, file.deletions)
+}
+
+fn human_size(bytes: u64) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1_048_575 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+fn file_name(path: &str) -> String {
+    path.rsplit('/').next().unwrap_or(path).to_lowercase()
+}
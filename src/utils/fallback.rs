@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+use crate::config::MessageStyle;
+use crate::utils::diff::{DiffSummary, FileDiff, FileStatus};
+use crate::utils::exclude::is_lockfile;
+use crate::utils::symbols::{ChangeKind, SymbolChange};
+
+const BUILD_FILES: &[&str] = &[
+    "cargo.toml",
+    "build.rs",
+    "package.json",
+    "tsconfig.json",
+    "pyproject.toml",
+    "setup.py",
+    "setup.cfg",
+    "go.mod",
+    "makefile",
+    "dockerfile",
+    "rust-toolchain.toml",
+];
+const CI_FILES: &[&str] = &[".gitlab-ci.yml", ".travis.yml", "azure-pipelines.yml", "jenkinsfile"];
+const CI_DIRS: &[&str] = &[".github", ".circleci", ".buildkite"];
+const DOC_FILES: &[&str] = &["readme", "changelog", "license", "contributing"];
+const DOC_EXTENSIONS: &[&str] = &["md", "markdown", "rst", "adoc"];
+const DOC_DIRS: &[&str] = &["docs", "doc"];
+const TEST_DIRS: &[&str] = &["tests", "test", "__tests__", "spec", "testdata"];
+
+// Directories that only hold modules, the scope is whatever comes after them
+const CONTAINER_DIRS: &[&str] = &["src", "lib", "pkg", "internal", "app", "tests", "test", "docs", "doc"];
+const WORKSPACE_DIRS: &[&str] = &["crates", "packages"];
+const ENTRY_POINTS: &[&str] = &["lib", "main", "mod", "index", "__init__"];
+
+// Subjects longer than this get summarized by counts instead of names
+const MAX_SUBJECT_LENGTH: usize = 72;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Area {
+    Source,
+    Test,
+    Docs,
+    Ci,
+    Build,
+}
+
+/// Builds a commit subject without a model: the type comes from which kind
+/// of files changed, the scope from the module most of the change is in, and
+/// the description from renames and symbol changes.
+pub fn generate_fallback_message(diff: &DiffSummary, symbols: &[SymbolChange], style: MessageStyle) -> String {
+    if diff.is_empty() {
+        return styled(style, "chore", None, "update files");
+    }
+
+    let commit_type = commit_type(diff, symbols);
+    let description = description(diff, symbols);
+    let scope = dominant_module(diff).filter(|_| commit_type != "ci");
+    styled(style, commit_type, scope, &description)
+}
+
+/// `feat(config): add load_profile`, `✨ Add load_profile` or `Add load_profile`
+fn styled(style: MessageStyle, commit_type: &str, scope: Option<String>, description: &str) -> String {
+    match (style, scope) {
+        (MessageStyle::Conventional, Some(scope)) => format!("{}({}): {}", commit_type, scope, description),
+        (MessageStyle::Conventional, None) => format!("{}: {}", commit_type, description),
+        (MessageStyle::Gitmoji, _) => format!("{} {}", gitmoji(commit_type), capitalize(description)),
+        (MessageStyle::Plain, _) => capitalize(description),
+    }
+}
+
+fn gitmoji(commit_type: &str) -> &'static str {
+    match commit_type {
+        "feat" => "✨",
+        "refactor" => "♻️",
+        "test" => "✅",
+        "docs" => "📝",
+        "ci" => "👷",
+        "build" => "📦",
+        _ => "🔧",
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn commit_type(diff: &DiffSummary, symbols: &[SymbolChange]) -> &'static str {
+    let areas: Vec<Area> = diff.files.iter().map(|f| area(&f.path)).collect();
+    let only = |wanted: Area| areas.iter().all(|&a| a == wanted);
+
+    if only(Area::Test) {
+        return "test";
+    }
+    if only(Area::Docs) {
+        return "docs";
+    }
+    if only(Area::Ci) {
+        return "ci";
+    }
+    if areas.iter().all(|&a| a == Area::Build) {
+        return "build";
+    }
+    if !areas.contains(&Area::Source) {
+        return "chore";
+    }
+
+    // Only source files decide between feat, fix and refactor
+    let source: Vec<&FileDiff> = diff
+        .files
+        .iter()
+        .filter(|f| area(&f.path) == Area::Source)
+        .collect();
+    let source_symbols: Vec<&SymbolChange> = symbols
+        .iter()
+        .filter(|s| source.iter().any(|f| f.path == s.path))
+        .collect();
+
+    if source.iter().all(|f| is_move(f)) {
+        return "refactor";
+    }
+    if source.iter().all(|f| f.status == FileStatus::Added)
+        || source_symbols.iter().any(|s| s.change == ChangeKind::Added)
+    {
+        return "feat";
+    }
+    if !source_symbols.is_empty()
+        && source_symbols
+            .iter()
+            .all(|s| matches!(s.change, ChangeKind::Renamed { .. } | ChangeKind::Removed))
+    {
+        return "refactor";
+    }
+    if source.iter().all(|f| f.status == FileStatus::Deleted) {
+        return "refactor";
+    }
+
+    // Without a model there is no telling a fix from any other edit
+    "chore"
+}
+
+fn area(path: &str) -> Area {
+    let lower = path.to_lowercase();
+    let file_name = lower.rsplit('/').next().unwrap_or(&lower);
+    let dirs: Vec<&str> = lower.split('/').rev().skip(1).collect();
+    let (stem, extension) = file_name.rsplit_once('.').unwrap_or((file_name, ""));
+
+    if CI_FILES.contains(&file_name) || dirs.last().is_some_and(|top| CI_DIRS.contains(top)) {
+        Area::Ci
+    } else if BUILD_FILES.contains(&file_name) || is_lockfile(path) || file_name.starts_with("requirements") {
+        Area::Build
+    } else if dirs.iter().any(|dir| TEST_DIRS.contains(dir)) || is_test_file(stem) {
+        Area::Test
+    } else if DOC_EXTENSIONS.contains(&extension)
+        || DOC_FILES.contains(&stem)
+        || dirs.iter().any(|dir| DOC_DIRS.contains(dir))
+    {
+        Area::Docs
+    } else {
+        Area::Source
+    }
+}
+
+fn is_test_file(stem: &str) -> bool {
+    stem.starts_with("test_")
+        || stem.ends_with("_test")
+        || stem.ends_with("_tests")
+        || stem.ends_with(".test")
+        || stem.ends_with(".spec")
+        || stem == "tests"
+}
+
+fn is_move(file: &FileDiff) -> bool {
+    file.status == FileStatus::Renamed && file.additions + file.deletions == 0
+}
+
+/// The module holding most of the changed lines, if one holds at least half
+fn dominant_module(diff: &DiffSummary) -> Option<String> {
+    let mut weights: HashMap<Option<String>, usize> = HashMap::new();
+    for file in &diff.files {
+        *weights.entry(module(file)).or_default() += (file.additions + file.deletions).max(1);
+    }
+
+    let total: usize = weights.values().sum();
+    let (module, weight) = weights
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))?;
+
+    if weight * 2 >= total {
+        module
+    } else {
+        None
+    }
+}
+
+/// `src/utils/git.rs` is `utils`, `src/config.rs` is `config`, `crates/cli/...`
+/// is `cli`; top-level files and crate roots have no module. A deleted file is
+/// gone, so it only counts towards its directory.
+fn module(file: &FileDiff) -> Option<String> {
+    let mut parts: Vec<&str> = file.path.split('/').collect();
+
+    if parts.len() > 2 && WORKSPACE_DIRS.contains(&parts[0]) {
+        return Some(parts[1].to_string());
+    }
+    if parts.len() < 2 || !CONTAINER_DIRS.contains(&parts[0]) {
+        return parts.first().filter(|_| parts.len() > 1).map(|dir| dir.to_string());
+    }
+
+    parts.remove(0);
+    if parts.len() > 1 {
+        return Some(parts[0].to_string());
+    }
+
+    let stem = parts[0].split('.').next().unwrap_or(parts[0]);
+    if stem.is_empty() || ENTRY_POINTS.contains(&stem) || file.status == FileStatus::Deleted {
+        None
+    } else {
+        Some(stem.to_string())
+    }
+}
+
+fn description(diff: &DiffSummary, symbols: &[SymbolChange]) -> String {
+    // Moved files say it best themselves
+    if diff.files.iter().all(is_move) {
+        return match diff.files.as_slice() {
+            [file] => describe_move(file),
+            files => format!("move {} files", files.len()),
+        };
+    }
+
+    let by_change = |wanted: fn(&ChangeKind) -> bool| -> Vec<&SymbolChange> {
+        symbols.iter().filter(|s| wanted(&s.change)).collect()
+    };
+    let renamed = by_change(|c| matches!(c, ChangeKind::Renamed { .. }));
+    let added = by_change(|c| *c == ChangeKind::Added);
+    let removed = by_change(|c| *c == ChangeKind::Removed);
+    let modified = by_change(|c| *c == ChangeKind::Modified);
+
+    if let [symbol] = renamed.as_slice() {
+        if added.is_empty() && removed.is_empty() && modified.is_empty() {
+            if let ChangeKind::Renamed { from } = &symbol.change {
+                return format!("rename {} to {}", short_name(from), short_name(&symbol.name));
+            }
+        }
+    }
+
+    for (verb, changes) in [("add", &added), ("remove", &removed), ("rename", &renamed), ("update", &modified)] {
+        if !changes.is_empty() {
+            let names: Vec<&str> = changes.iter().map(|s| short_name(&s.name)).collect();
+            return name_list(verb, &names, "symbols");
+        }
+    }
+
+    describe_files(diff)
+}
+
+fn describe_move(file: &FileDiff) -> String {
+    let old_path = file.old_path.as_deref().unwrap_or(&file.path);
+    let (old_dir, old_name) = old_path.rsplit_once('/').unwrap_or(("", old_path));
+    let (new_dir, new_name) = file.path.rsplit_once('/').unwrap_or(("", &file.path));
+
+    if old_name == new_name {
+        let target = if new_dir.is_empty() { "the repository root" } else { new_dir };
+        format!("move {} to {}", new_name, target)
+    } else if old_dir == new_dir {
+        format!("rename {} to {}", old_name, new_name)
+    } else {
+        format!("move {} to {}", old_path, file.path)
+    }
+}
+
+fn describe_files(diff: &DiffSummary) -> String {
+    let names = |status: FileStatus| -> Vec<&str> {
+        diff.files
+            .iter()
+            .filter(|f| f.status == status)
+            .map(|f| file_name(&f.path))
+            .collect()
+    };
+    let added = names(FileStatus::Added);
+    let deleted = names(FileStatus::Deleted);
+
+    if added.len() == diff.files.len() {
+        return name_list("add", &added, "files");
+    }
+    if deleted.len() == diff.files.len() {
+        return name_list("remove", &deleted, "files");
+    }
+
+    if let [file] = diff.files.as_slice() {
+        if file.additions + file.deletions == 0 {
+            if let Some(mode_change) = file.mode_change() {
+                return format!("{} {}", file_name(&file.path), mode_change.replace("made ", "make "));
+            }
+        }
+    }
+
+    let all: Vec<&str> = diff.files.iter().map(|f| file_name(&f.path)).collect();
+    name_list("update", &all, "files")
+}
+
+/// "add a", "add a and b", "add a, b and c", falling back to "add 5 files"
+fn name_list(verb: &str, names: &[&str], noun: &str) -> String {
+    let text = match names {
+        [] => format!("{} {}", verb, noun),
+        [one] => format!("{} {}", verb, one),
+        [first, second] => format!("{} {} and {}", verb, first, second),
+        [first, second, third] => format!("{} {}, {} and {}", verb, first, second, third),
+        _ => format!("{} {} {}", verb, names.len(), noun),
+    };
+
+    if text.len() > MAX_SUBJECT_LENGTH / 2 && names.len() > 1 {
+        format!("{} {} {}", verb, names.len(), noun)
+    } else {
+        text
+    }
+}
+
+fn short_name(name: &str) -> &str {
+    name.rsplit(['.', ':']).next().unwrap_or(name)
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
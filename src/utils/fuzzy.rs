@@ -0,0 +1,71 @@
+/// Levenshtein distance, in characters
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + usize::from(ca != *cb);
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// How well `query` matches `candidate` when its characters appear in order,
+/// like fzf. `None` when they don't. Runs of characters and matches at the
+/// start of a word score higher.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let query: Vec<char> = query.to_lowercase().chars().filter(|c| !c.is_whitespace()).collect();
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+
+    let mut score = 0;
+    let mut next = 0;
+    let mut last_match: Option<usize> = None;
+    for q in &query {
+        let found = (next..candidate.len()).find(|&i| candidate[i] == *q)?;
+        score += 1;
+        if last_match.is_some_and(|last| last + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || matches!(candidate[found - 1], '/' | '-' | '_' | '.' | ':' | ' ') {
+            score += 3;
+        }
+        score -= (found - next) as i64 / 4;
+        last_match = Some(found);
+        next = found + 1;
+    }
+    Some(score)
+}
+
+/// `candidates` that match `query`, best first
+pub fn fuzzy_filter<'a, T>(query: &str, candidates: &'a [T], text: impl Fn(&T) -> &str) -> Vec<&'a T> {
+    let mut scored: Vec<(i64, &T)> = candidates
+        .iter()
+        .filter_map(|candidate| fuzzy_score(query, text(candidate)).map(|score| (score, candidate)))
+        .collect();
+    // Stable, so equally good matches keep their original order
+    scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    scored.into_iter().map(|(_, candidate)| candidate).collect()
+}
+
+/// Up to `limit` candidates that look like a typo of `name`, closest first
+pub fn suggestions<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>, limit: usize) -> Vec<&'a str> {
+    let name = name.to_lowercase();
+    let max_distance = (name.chars().count() / 3).max(2);
+
+    let mut close: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let lower = candidate.to_lowercase();
+            // `gpt-4o` is close to `openai/gpt-4o` even without the vendor
+            let short = lower.rsplit('/').next().unwrap_or(&lower);
+            let distance = edit_distance(&name, &lower).min(edit_distance(&name, short));
+            (distance <= max_distance).then_some((distance, candidate))
+        })
+        .collect();
+    close.sort_by_key(|(distance, _)| *distance);
+    close.into_iter().take(limit).map(|(_, candidate)| candidate).collect()
+}
@@ -1,12 +1,31 @@
-use git2::{DiffOptions, Repository};
-use crate::errors::Result;
+use git2::{DiffFindOptions, DiffOptions, Index, Oid, Repository, StatusOptions};
+use std::io::Write;
+use std::process::Command;
+use tempfile::NamedTempFile;
+use crate::config::CommitBackend;
+use crate::errors::{CommitError, Result};
+use crate::utils::diff::DiffSummary;
+use crate::utils::exclude::exclude_noise;
+use crate::utils::hooks::{run_commit_msg_hook, run_hook};
 
 #[derive(Debug)]
 pub struct GitChanges {
     pub staged_files: Vec<String>,
     pub unstaged_files: Vec<String>,
-    pub staged_diff: String,
-    pub unstaged_diff: String,
+    pub staged_diff: DiffSummary,
+    pub unstaged_diff: DiffSummary,
+}
+
+impl GitChanges {
+    /// What a commit would take: the staged files, or every change when
+    /// nothing is staged
+    pub fn commit_files(&self) -> &[String] {
+        if self.staged_diff.is_empty() {
+            &self.unstaged_files
+        } else {
+            &self.staged_files
+        }
+    }
 }
 
 pub fn get_git_changes(repo: &Repository) -> Result<GitChanges> {
@@ -14,23 +33,49 @@ pub fn get_git_changes(repo: &Repository) -> Result<GitChanges> {
     let mut unstaged_files = Vec::new();
 
     // Get status to identify changed files
-    let statuses = repo.statuses(None)?;
+    let mut status_opts = StatusOptions::new();
+    status_opts
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .renames_head_to_index(true)
+        .renames_index_to_workdir(true);
+    let statuses = repo.statuses(Some(&mut status_opts))?;
 
     for entry in statuses.iter() {
-        let path = entry.path().unwrap_or("").to_string();
-
-        if entry.status().is_index_new() || entry.status().is_index_modified() || entry.status().is_index_deleted() {
-            staged_files.push(path.clone());
+        let status = entry.status();
+        // Renamed entries report the old path, list files by where they are now
+        let new_path = |delta: Option<git2::DiffDelta>| {
+            delta
+                .and_then(|d| d.new_file().path().map(|p| p.to_string_lossy().to_string()))
+                .unwrap_or_else(|| entry.path().unwrap_or("").to_string())
+        };
+
+        if status.is_index_new()
+            || status.is_index_modified()
+            || status.is_index_deleted()
+            || status.is_index_renamed()
+            || status.is_index_typechange()
+        {
+            staged_files.push(new_path(entry.head_to_index()));
         }
 
-        if entry.status().is_wt_new() || entry.status().is_wt_modified() || entry.status().is_wt_deleted() {
-            unstaged_files.push(path);
+        if status.is_wt_new()
+            || status.is_wt_modified()
+            || status.is_wt_deleted()
+            || status.is_wt_renamed()
+            || status.is_wt_typechange()
+        {
+            unstaged_files.push(new_path(entry.index_to_workdir()));
         }
     }
 
     // Get diffs
-    let staged_diff = get_staged_diff(repo)?;
-    let unstaged_diff = get_unstaged_diff(repo)?;
+    let mut staged_diff = get_staged_diff(repo)?;
+    let mut unstaged_diff = get_unstaged_diff(repo)?;
+
+    // Keep lockfiles, generated and binary content out of the prompt
+    exclude_noise(repo, &mut staged_diff)?;
+    exclude_noise(repo, &mut unstaged_diff)?;
 
     Ok(GitChanges {
         staged_files,
@@ -40,65 +85,71 @@ pub fn get_git_changes(repo: &Repository) -> Result<GitChanges> {
     })
 }
 
-fn get_staged_diff(repo: &Repository) -> Result<String> {
+fn get_staged_diff(repo: &Repository) -> Result<DiffSummary> {
     let head = repo.head()?;
     let head_tree = head.peel_to_tree()?;
     let mut index = repo.index()?;
     let index_tree = repo.find_tree(index.write_tree()?)?;
 
-    let diff = repo.diff_tree_to_tree(Some(&head_tree), Some(&index_tree), None)?;
-    let mut diff_text = Vec::new();
-    diff.print(git2::DiffFormat::Patch, |_delta, _hunk, line| {
-        diff_text.extend_from_slice(line.content());
-        true
-    })?;
-
-    Ok(String::from_utf8_lossy(&diff_text).to_string())
+    let mut opts = DiffOptions::new();
+    opts.include_typechange(true);
+    let mut diff = repo.diff_tree_to_tree(Some(&head_tree), Some(&index_tree), Some(&mut opts))?;
+    diff.find_similar(Some(&mut find_options()))?;
+    DiffSummary::from_diff(&diff)
 }
 
-fn get_unstaged_diff(repo: &Repository) -> Result<String> {
+fn get_unstaged_diff(repo: &Repository) -> Result<DiffSummary> {
+    // Untracked files are part of an unstaged commit, and a plain `mv`
+    // only shows up as a rename when the new path is in the diff
     let mut opts = DiffOptions::new();
-    let diff = repo.diff_index_to_workdir(None, Some(&mut opts))?;
-
-    let mut diff_text = Vec::new();
-    diff.print(git2::DiffFormat::Patch, |_delta, _hunk, line| {
-        diff_text.extend_from_slice(line.content());
-        true
-    })?;
+    opts
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .show_untracked_content(true)
+        .include_typechange(true);
+    let mut diff = repo.diff_index_to_workdir(None, Some(&mut opts))?;
+    diff.find_similar(Some(find_options().for_untracked(true)))?;
+    DiffSummary::from_diff(&diff)
+}
 
-    Ok(String::from_utf8_lossy(&diff_text).to_string())
+fn find_options() -> DiffFindOptions {
+    let mut opts = DiffFindOptions::new();
+    opts.renames(true).copies(true);
+    opts
 }
 
-pub fn filter_diff_content(diff: &str, max_lines: usize) -> String {
-    let lines: Vec<&str> = diff.lines().collect();
+#[derive(Debug, Clone, Default)]
+pub struct CommitOptions {
+    /// Skip the pre-commit and commit-msg hooks, like `git commit --no-verify`
+    pub no_verify: bool,
+    pub backend: CommitBackend,
+    /// Stage every change first instead of committing the index as is
+    pub stage_all: bool,
+}
 
-    if lines.len() <= max_lines {
-        return diff.to_string();
+/// Returns the id of the new commit
+pub fn perform_git_commit(repo: &Repository, message: &str, options: &CommitOptions) -> Result<Oid> {
+    let mut index = repo.index()?;
+    if options.stage_all {
+        index.add_all(["."], git2::IndexAddOption::DEFAULT, None)?;
+        index.write()?;
     }
 
-    // Keep header and truncate middle
-    let header_lines = 10;
-    let footer_lines = 10;
-    let mut result = Vec::new();
-
-    // Add header
-    result.extend_from_slice(&lines[..header_lines.min(lines.len())]);
-    result.push("... [diff truncated due to size] ...");
-
-    // Add footer if there's space
-    if lines.len() > header_lines + footer_lines {
-        let start = lines.len().saturating_sub(footer_lines);
-        result.extend_from_slice(&lines[start..]);
+    match options.backend {
+        CommitBackend::Git2 => commit_with_git2(repo, &mut index, message, options),
+        CommitBackend::Cli => commit_with_cli(repo, message, options),
     }
-
-    result.join("\n")
 }
 
-pub fn perform_git_commit(repo: &Repository, message: &str) -> Result<()> {
-    // Stage all changes first
-    let mut index = repo.index()?;
-    index.add_all(["."], git2::IndexAddOption::DEFAULT, None)?;
-    index.write()?;
+fn commit_with_git2(repo: &Repository, index: &mut Index, message: &str, options: &CommitOptions) -> Result<Oid> {
+    let message = if options.no_verify {
+        message.to_string()
+    } else {
+        run_hook(repo, "pre-commit", &[])?;
+        // pre-commit may have reformatted and restaged files
+        index.read(true)?;
+        run_commit_msg_hook(repo, message)?
+    };
 
     // Create commit
     let head = repo.head()?;
@@ -107,49 +158,53 @@ pub fn perform_git_commit(repo: &Repository, message: &str) -> Result<()> {
     let tree = repo.find_tree(index.write_tree()?)?;
     let signature = repo.signature()?;
 
-    repo.commit(
+    let oid = repo.commit(
         Some("HEAD"),
         &signature,
         &signature,
-        message,
+        &message,
         &tree,
         &[&head_commit],
     )?;
 
-    Ok(())
-}
+    // Like git, a failing post-commit hook doesn't undo the commit
+    let _ = run_hook(repo, "post-commit", &[]);
 
-pub fn generate_fallback_message(files: &[String], diff: &str) -> String {
+    Ok(oid)
+}
 
-    if diff.is_empty() {
-        return format!("Update {}", files.join(", "));
+fn commit_with_cli(repo: &Repository, message: &str, options: &CommitOptions) -> Result<Oid> {
+    let mut message_file = NamedTempFile::new()?;
+    writeln!(message_file, "{}", message.trim_end())?;
+
+    // git runs hooks, signing and fsmonitor itself
+    let mut command = Command::new("git");
+    command
+        .arg("commit")
+        .arg("-F")
+        .arg(message_file.path())
+        .current_dir(repo.workdir().unwrap_or_else(|| repo.path()));
+    if options.no_verify {
+        command.arg("--no-verify");
     }
 
-    let lines: Vec<&str> = diff.lines().collect();
-    let mut added_lines = 0;
-    let mut deleted_lines = 0;
-
-    for line in &lines {
-        if line.starts_with('+') && !line.starts_with("+++") {
-            added_lines += 1;
-        } else if line.starts_with('-') && !line.starts_with("---") {
-            deleted_lines += 1;
-        }
+    let output = command
+        .output()
+        .map_err(|e| CommitError::GitOperationError(format!("failed to run git: {}", e)))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let details = if stderr.trim().is_empty() { stdout } else { stderr };
+        return Err(CommitError::GitOperationError(format!(
+            "git commit exited with {}: {}",
+            output.status,
+            details.trim()
+        )));
     }
 
-    // Detect common patterns
-    let diff_lower = diff.to_lowercase();
-    if diff_lower.contains("test") || diff_lower.contains("spec") {
-        "Add/update tests".to_string()
-    } else if diff_lower.contains("readme") || diff_lower.contains("doc") {
-        "Update documentation".to_string()
-    } else if diff_lower.contains("fix") || diff_lower.contains("bug") {
-        "Fix bug".to_string()
-    } else if added_lines > deleted_lines * 2 {
-        format!("Add new functionality to {}", files.join(", "))
-    } else if deleted_lines > added_lines * 2 {
-        format!("Remove/cleanup code from {}", files.join(", "))
-    } else {
-        format!("Update {}", files.join(", "))
-    }
-}
\ No newline at end of file
+    // git moved HEAD behind our back, refs are read from disk again
+    repo.head()?
+        .target()
+        .ok_or_else(|| CommitError::GitOperationError("HEAD doesn't point at a commit".to_string()))
+}
@@ -0,0 +1,38 @@
+use regex::{Regex, RegexSet};
+
+/// Matches a whole path or branch name against a glob: `*` stays within one
+/// `/`-separated segment, `**` spans any number of them and `?` is one
+/// character, so `docs/**` matches everything under docs and `**/*.md`
+/// every markdown file
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    Regex::new(&glob_to_regex(pattern)).is_ok_and(|regex| regex.is_match(path))
+}
+
+/// Any of several globs, compiled once for matching many paths
+pub fn glob_set<S: AsRef<str>>(patterns: &[S]) -> RegexSet {
+    RegexSet::new(patterns.iter().map(|pattern| glob_to_regex(pattern.as_ref()))).unwrap_or_else(|_| RegexSet::empty())
+}
+
+fn glob_to_regex(pattern: &str) -> String {
+    let mut regex = String::from("^");
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                // `docs/**/x` also matches `docs/x`
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex
+}
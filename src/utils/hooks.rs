@@ -0,0 +1,124 @@
+use git2::Repository;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use crate::errors::{CommitError, Result};
+
+pub fn hooks_dir(repo: &Repository) -> Result<PathBuf> {
+    let workdir = repo.workdir().unwrap_or_else(|| repo.path());
+
+    // core.hooksPath overrides .git/hooks, relative paths resolve against the worktree
+    match repo.config()?.get_path("core.hooksPath") {
+        Ok(path) if path.is_absolute() => Ok(path),
+        Ok(path) => Ok(workdir.join(path)),
+        Err(_) => Ok(repo.path().join("hooks")),
+    }
+}
+
+const HOOK_MARKER: &str = "# Installed by rusty-commit";
+
+/// Fills in a generated message unless git already has one from -m, -F, a
+/// template, a merge or an amend. A failure never blocks the commit.
+const PREPARE_COMMIT_MSG: &str = r#"#!/bin/sh
+# Installed by rusty-commit
+[ -z "$2" ] || exit 0
+rusty-commit generate --message-file "$1" || true
+"#;
+
+/// Installs the prepare-commit-msg hook, returns its path. Hooks written by
+/// anyone else are left alone.
+pub fn install_hook(repo: &Repository) -> Result<PathBuf> {
+    let dir = hooks_dir(repo)?;
+    let hook = dir.join("prepare-commit-msg");
+
+    // Anything there that we can't read as ours, binary or not, is kept
+    let ours = match fs::read(&hook) {
+        Ok(existing) => String::from_utf8_lossy(&existing).contains(HOOK_MARKER),
+        Err(e) => e.kind() == std::io::ErrorKind::NotFound,
+    };
+    if !ours {
+        return Err(CommitError::GitOperationError(format!(
+            "{} already exists and wasn't installed by rusty-commit",
+            hook.display()
+        )));
+    }
+
+    fs::create_dir_all(&dir)?;
+    fs::write(&hook, PREPARE_COMMIT_MSG)?;
+    make_executable(&hook)?;
+    Ok(hook)
+}
+
+pub fn run_hook(repo: &Repository, name: &str, args: &[&Path]) -> Result<()> {
+    let hook = hooks_dir(repo)?.join(name);
+
+    // Git silently skips hooks that are missing or not executable
+    if !is_executable(&hook) {
+        return Ok(());
+    }
+
+    let workdir = repo.workdir().unwrap_or_else(|| repo.path());
+    // Whatever hooks print is for the user, stdout may be carrying JSON
+    let status = hook_command(&hook)
+        .args(args)
+        .current_dir(workdir)
+        .stdout(Stdio::from(std::io::stderr()))
+        .status()?;
+
+    if !status.success() {
+        return Err(CommitError::HookFailed {
+            hook: name.to_string(),
+            status,
+        });
+    }
+
+    Ok(())
+}
+
+/// Runs commit-msg on the message and returns it as rewritten by the hook
+pub fn run_commit_msg_hook(repo: &Repository, message: &str) -> Result<String> {
+    let message_file = repo.path().join("COMMIT_EDITMSG");
+    fs::write(&message_file, format!("{}\n", message.trim_end()))?;
+    run_hook(repo, "commit-msg", &[&message_file])?;
+    Ok(fs::read_to_string(&message_file)?)
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata()
+        .map(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn hook_command(hook: &Path) -> Command {
+    Command::new(hook)
+}
+
+#[cfg(not(unix))]
+fn hook_command(hook: &Path) -> Command {
+    // Hooks are shell scripts, run them through sh like Git for Windows does
+    let mut command = Command::new("sh");
+    command.arg(hook);
+    command
+}
@@ -0,0 +1,160 @@
+use git2::{ApplyLocation, ApplyOptions, Diff, DiffDelta, DiffOptions, Patch, Repository};
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use crate::errors::Result;
+
+#[derive(Debug, Clone)]
+pub struct Hunk {
+    /// `path#n` for the n-th hunk of a file, or just `path` for binary and mode-only changes
+    pub id: String,
+    pub path: String,
+    pub text: String,
+    pub additions: usize,
+    pub deletions: usize,
+    // First line on the new side. The new side is the same tree in every
+    // re-diff, so this still finds the hunk once others of the file are applied
+    pub(crate) new_start: u32,
+}
+
+/// Picks hunks out of a diff by path and new-side start for `ApplyOptions`
+pub(crate) struct HunkSelection {
+    wanted: HashMap<String, BTreeSet<u32>>,
+    current: RefCell<String>,
+}
+
+impl HunkSelection {
+    pub(crate) fn new(selected: &[&Hunk]) -> Self {
+        let mut wanted: HashMap<String, BTreeSet<u32>> = HashMap::new();
+        for hunk in selected {
+            wanted.entry(hunk.path.clone()).or_default().insert(hunk.new_start);
+        }
+
+        Self {
+            wanted,
+            current: RefCell::new(String::new()),
+        }
+    }
+
+    /// Files with a selected hunk, for limiting the diff to them
+    pub(crate) fn paths(&self) -> Vec<&str> {
+        let mut paths: Vec<&str> = self.wanted.keys().map(String::as_str).collect();
+        paths.sort_unstable();
+        paths
+    }
+
+    pub(crate) fn apply_options(&self) -> ApplyOptions<'_> {
+        let mut options = ApplyOptions::new();
+
+        // libgit2 reports hunks right after their delta, track which file we're in
+        options.delta_callback(|delta| {
+            let path = delta.map(|d| delta_path(&d)).unwrap_or_default();
+            let take = self.wanted.contains_key(&path);
+            *self.current.borrow_mut() = path;
+            take
+        });
+        options.hunk_callback(|hunk| {
+            let current = self.current.borrow();
+            hunk.is_some_and(|hunk| {
+                self.wanted
+                    .get(current.as_str())
+                    .is_some_and(|starts| starts.contains(&hunk.new_start()))
+            })
+        });
+
+        options
+    }
+}
+
+pub fn collect_unstaged_hunks(repo: &Repository) -> Result<Vec<Hunk>> {
+    let diff = unstaged_diff(repo, &[])?;
+    diff_hunks(&diff)
+}
+
+/// Stages exactly the given hunks, like answering `y` to them in `git add -p`
+pub fn stage_hunks(repo: &Repository, hunks: &[&Hunk]) -> Result<()> {
+    let selection = HunkSelection::new(hunks);
+
+    // Only diff the picked files, libgit2 applies deletions regardless of the delta callback
+    let paths = selection.paths();
+    if paths.is_empty() {
+        return Ok(());
+    }
+
+    let diff = unstaged_diff(repo, &paths)?;
+    let mut apply_options = selection.apply_options();
+    repo.apply(&diff, ApplyLocation::Index, Some(&mut apply_options))?;
+
+    Ok(())
+}
+
+fn unstaged_diff<'r>(repo: &'r Repository, paths: &[&str]) -> Result<Diff<'r>> {
+    let mut opts = DiffOptions::new();
+    opts.show_binary(true).disable_pathspec_match(true);
+    for path in paths {
+        opts.pathspec(path);
+    }
+    Ok(repo.diff_index_to_workdir(None, Some(&mut opts))?)
+}
+
+pub fn diff_hunks(diff: &Diff) -> Result<Vec<Hunk>> {
+    let mut hunks = Vec::new();
+
+    for (idx, delta) in diff.deltas().enumerate() {
+        let path = delta_path(&delta);
+        let patch = Patch::from_diff(diff, idx)?;
+
+        let num_hunks = patch.as_ref().map_or(0, |p| p.num_hunks());
+        if num_hunks == 0 {
+            hunks.push(Hunk {
+                id: path.clone(),
+                path: path.clone(),
+                text: format!("{} ({:?})", path, delta.status()),
+                additions: 0,
+                deletions: 0,
+                new_start: 0,
+            });
+            continue;
+        }
+
+        let patch = patch.expect("patch with hunks");
+        for hunk_idx in 0..num_hunks {
+            let (hunk, line_count) = patch.hunk(hunk_idx)?;
+            let mut text = String::from_utf8_lossy(hunk.header()).to_string();
+            let mut additions = 0;
+            let mut deletions = 0;
+
+            for line_idx in 0..line_count {
+                let line = patch.line_in_hunk(hunk_idx, line_idx)?;
+                let content = String::from_utf8_lossy(line.content());
+                text.push(line.origin());
+                text.push_str(&content);
+
+                match line.origin() {
+                    '+' => additions += 1,
+                    '-' => deletions += 1,
+                    _ => {}
+                }
+            }
+
+            hunks.push(Hunk {
+                id: format!("{}#{}", path, hunk_idx + 1),
+                path: path.clone(),
+                text,
+                additions,
+                deletions,
+                new_start: hunk.new_start(),
+            });
+        }
+    }
+
+    Ok(hunks)
+}
+
+pub(crate) fn delta_path(delta: &DiffDelta) -> String {
+    delta
+        .new_file()
+        .path()
+        .or_else(|| delta.old_file().path())
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
@@ -0,0 +1,48 @@
+use git2::{IndexAddOption, Repository};
+use std::fs;
+use crate::errors::Result;
+
+const FILE_NAME: &str = "RUSTY_COMMIT_MSG";
+
+/// Identifies what a commit would contain right now: HEAD plus the tree of
+/// the index, or of the index with every change staged when `stage_all` is set
+pub fn commit_state(repo: &Repository, stage_all: bool) -> Result<String> {
+    let mut index = repo.index()?;
+    if stage_all {
+        index.add_all(["."], IndexAddOption::DEFAULT, None)?;
+    }
+    let tree = index.write_tree();
+    // Only look, the commit itself decides what gets staged
+    index.read(true)?;
+
+    let head = repo
+        .head()
+        .ok()
+        .and_then(|head| head.target())
+        .map(|oid| oid.to_string())
+        .unwrap_or_default();
+
+    Ok(format!("tree {} head {}", tree?, head))
+}
+
+/// Remembers a generated message in `.git/RUSTY_COMMIT_MSG` for `commit` to pick up
+pub fn save_last_message(repo: &Repository, state: &str, message: &str) -> Result<()> {
+    fs::write(repo.path().join(FILE_NAME), format!("{}\n{}\n", state, message.trim_end()))?;
+    Ok(())
+}
+
+/// The last generated message, if the commit it was written for hasn't changed
+pub fn load_last_message(repo: &Repository, state: &str) -> Option<String> {
+    let content = fs::read_to_string(repo.path().join(FILE_NAME)).ok()?;
+    let (saved_state, message) = content.split_once('\n')?;
+
+    let message = message.trim_end();
+    if saved_state != state || message.is_empty() {
+        return None;
+    }
+    Some(message.to_string())
+}
+
+pub fn clear_last_message(repo: &Repository) {
+    let _ = fs::remove_file(repo.path().join(FILE_NAME));
+}
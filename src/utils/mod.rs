@@ -1,3 +1,16 @@
 pub mod env_variable;
 pub mod prompt;
 pub mod git;
+pub mod hooks;
+pub mod split;
+pub mod hunks;
+pub mod diff;
+pub mod budget;
+pub mod exclude;
+pub mod symbols;
+pub mod fallback;
+pub mod last_message;
+pub mod secrets;
+pub mod fuzzy;
+pub mod glob;
+pub mod output;
@@ -0,0 +1,114 @@
+use serde::Serialize;
+use crate::api::usage::TallyState;
+use crate::utils::diff::DiffSummary;
+
+/// Bumped only when a field changes meaning or goes away, new fields may be added
+pub const REPORT_SCHEMA_VERSION: u32 = 1;
+
+/// What `--format json` prints for `generate` and `commit`
+#[derive(Debug, Clone, Serialize)]
+pub struct MessageReport {
+    pub schema_version: u32,
+    /// `generate` or `commit`
+    pub command: String,
+    pub message: ReportMessage,
+    pub files: Vec<String>,
+    pub stats: ReportStats,
+    pub provider: String,
+    /// `None` when no model wrote the message
+    pub model: Option<String>,
+    pub profile: Option<String>,
+    /// `model`, `fallback`, `previous` (saved by an earlier `generate`) or `argument`
+    pub message_source: String,
+    pub fallback_used: bool,
+    /// Why the model couldn't be used, when it was supposed to be
+    pub fallback_reason: Option<String>,
+    pub tokens: ReportTokens,
+    /// USD, only when the provider reported it for every request
+    pub cost: Option<f64>,
+    pub requests: u64,
+    pub cache_hits: u64,
+    pub latency_ms: u64,
+    /// Set once `commit` has created the commit
+    pub commit_oid: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportMessage {
+    pub subject: String,
+    /// Everything after the blank line following the subject
+    pub body: Option<String>,
+    pub full: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ReportStats {
+    pub files_changed: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ReportTokens {
+    pub prompt: u64,
+    pub completion: u64,
+    pub total: u64,
+}
+
+/// `{"schema_version": 1, "error": {...}}`, printed instead of a report on failure
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorReport {
+    pub schema_version: u32,
+    pub error: ReportError,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportError {
+    pub message: String,
+    pub exit_code: u8,
+}
+
+impl ReportMessage {
+    pub fn new(message: &str) -> Self {
+        let full = message.trim().to_string();
+        let (subject, body) = match full.split_once('\n') {
+            Some((subject, body)) if !body.trim().is_empty() => (subject.trim_end(), Some(body.trim().to_string())),
+            Some((subject, _)) => (subject.trim_end(), None),
+            None => (full.as_str(), None),
+        };
+        Self {
+            subject: subject.to_string(),
+            body,
+            full: full.clone(),
+        }
+    }
+}
+
+impl From<&DiffSummary> for ReportStats {
+    fn from(diff: &DiffSummary) -> Self {
+        Self {
+            files_changed: diff.stats.files_changed,
+            insertions: diff.stats.insertions,
+            deletions: diff.stats.deletions,
+        }
+    }
+}
+
+impl From<&TallyState> for ReportTokens {
+    fn from(state: &TallyState) -> Self {
+        Self {
+            prompt: state.totals.prompt_tokens,
+            completion: state.totals.completion_tokens,
+            total: state.totals.prompt_tokens + state.totals.completion_tokens,
+        }
+    }
+}
+
+impl ErrorReport {
+    pub fn new(message: String, exit_code: u8) -> Self {
+        Self {
+            schema_version: REPORT_SCHEMA_VERSION,
+            error: ReportError { message, exit_code },
+        }
+    }
+}
@@ -6,5 +6,5 @@ pub fn prompt_user(prompt_message: &str) -> io::Result<String> {
         .with_prompt(prompt_message)
         .interact_text() // or interact() for other types of inputs since without feature it's not
         // working idk why?
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+        .map_err(io::Error::other)
 }
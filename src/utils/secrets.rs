@@ -0,0 +1,125 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use crate::config::ENV_PREFIX;
+use crate::errors::{CommitError, Result};
+
+const KEYRING_SERVICE: &str = "rusty-commit";
+/// Account used when a `keyring:` reference doesn't name one
+pub const DEFAULT_KEYRING_ACCOUNT: &str = "openrouter";
+
+/// Where a secret setting really lives
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecretRef {
+    /// `keyring:account`, the OS keychain / secret service
+    Keyring(String),
+    /// `env:VAR`
+    Env(String),
+    /// `cmd:pass show openrouter`, the command's output
+    Cmd(String),
+    /// `file:~/.secrets/openrouter`
+    File(PathBuf),
+}
+
+impl SecretRef {
+    /// `None` for a plain value
+    pub fn parse(value: &str) -> Option<Self> {
+        let (scheme, rest) = value.split_once(':')?;
+        let rest = rest.trim();
+        match scheme {
+            "keyring" if rest.is_empty() => Some(SecretRef::Keyring(DEFAULT_KEYRING_ACCOUNT.to_string())),
+            "keyring" => Some(SecretRef::Keyring(rest.to_string())),
+            "env" => Some(SecretRef::Env(rest.to_string())),
+            "cmd" => Some(SecretRef::Cmd(rest.to_string())),
+            "file" => Some(SecretRef::File(expand_home(rest))),
+            _ => None,
+        }
+    }
+
+    /// Whether a repo config may use it. A committed file must not run
+    /// commands or read arbitrary files and variables, only name a keyring
+    /// entry or one of our own variables.
+    pub fn allowed_in_repo(&self) -> bool {
+        match self {
+            SecretRef::Keyring(_) => true,
+            SecretRef::Env(name) => name.starts_with(ENV_PREFIX),
+            SecretRef::Cmd(_) | SecretRef::File(_) => false,
+        }
+    }
+
+    pub fn resolve(&self) -> Result<String> {
+        let secret = match self {
+            SecretRef::Keyring(account) => off_runtime(|| {
+                keyring::Entry::new(KEYRING_SERVICE, account).and_then(|entry| entry.get_password())
+            })
+            .map_err(|e| CommitError::SecretError(format!("keyring:{}: {}", account, e)))?,
+            SecretRef::Env(name) => std::env::var(name)
+                .map_err(|_| CommitError::SecretError(format!("env:{}: variable is not set", name)))?,
+            SecretRef::Cmd(command) => run_secret_command(command)?,
+            SecretRef::File(path) => fs::read_to_string(path)
+                .map_err(|e| CommitError::SecretError(format!("file:{}: {}", path.display(), e)))?,
+        };
+
+        let secret = secret.trim().to_string();
+        if secret.is_empty() {
+            return Err(CommitError::SecretError(format!("{:?} resolved to an empty value", self)));
+        }
+        Ok(secret)
+    }
+}
+
+pub fn is_secret_ref(value: &str) -> bool {
+    SecretRef::parse(value).is_some()
+}
+
+/// The value itself unless it's a reference to where the value is kept
+pub fn resolve_secret(value: &str) -> Result<String> {
+    match SecretRef::parse(value) {
+        Some(reference) => reference.resolve(),
+        None => Ok(value.to_string()),
+    }
+}
+
+/// Saves the secret in the OS keyring and returns the reference to put in config
+pub fn store_in_keyring(account: &str, secret: &str) -> Result<String> {
+    off_runtime(|| keyring::Entry::new(KEYRING_SERVICE, account).and_then(|entry| entry.set_password(secret)))
+        .map_err(|e| CommitError::SecretError(format!("keyring:{}: {}", account, e)))?;
+    Ok(format!("keyring:{}", account))
+}
+
+/// The Secret Service backend blocks on its own runtime, which panics inside
+/// ours, so keyring calls run on a thread of their own
+fn off_runtime<T: Send>(call: impl FnOnce() -> keyring::Result<T> + Send) -> keyring::Result<T> {
+    std::thread::scope(|scope| {
+        scope
+            .spawn(call)
+            .join()
+            .unwrap_or_else(|_| Err(keyring::Error::PlatformFailure("keyring backend panicked".into())))
+    })
+}
+
+fn run_secret_command(command: &str) -> Result<String> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .output()
+        .map_err(|e| CommitError::SecretError(format!("cmd:{}: {}", command, e)))?;
+
+    // Never echo stdout, it may hold part of the secret
+    if !output.status.success() {
+        return Err(CommitError::SecretError(format!(
+            "cmd:{} exited with {}: {}",
+            command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
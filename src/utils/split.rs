@@ -0,0 +1,197 @@
+use git2::{Diff, DiffOptions, Oid, Repository, Tree};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use crate::config::MessageStyle;
+use crate::errors::{CommitError, Result};
+use crate::utils::diff::DiffSummary;
+use crate::utils::fallback::generate_fallback_message;
+use crate::utils::git::{perform_git_commit, CommitOptions};
+use crate::utils::hunks::{diff_hunks, Hunk, HunkSelection};
+use crate::utils::symbols::SymbolChange;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SplitPlan {
+    pub commits: Vec<SplitGroup>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SplitGroup {
+    pub message: String,
+    #[serde(alias = "files")]
+    pub hunks: Vec<String>,
+}
+
+/// Every staged hunk. A staged rename shows up as the old path's deletion
+/// and the new path's addition, both of which the split has to place.
+pub fn collect_staged_hunks(repo: &Repository) -> Result<Vec<Hunk>> {
+    let head_tree = repo.head()?.peel_to_tree()?;
+    let index_tree = repo.find_tree(repo.index()?.write_tree()?)?;
+    let diff = diff_trees(repo, &head_tree, &index_tree, &[])?;
+    diff_hunks(&diff)
+}
+
+pub fn parse_split_plan(response: &str) -> Result<SplitPlan> {
+    // Models like to wrap JSON in prose or code fences
+    let start = response.find('{').ok_or(CommitError::InvalidResponse)?;
+    let end = response.rfind('}').ok_or(CommitError::InvalidResponse)?;
+    if end < start {
+        return Err(CommitError::InvalidResponse);
+    }
+
+    Ok(serde_json::from_str(&response[start..=end])?)
+}
+
+/// Expands whole-file ids, drops unknown or repeated ids and puts anything the
+/// plan forgot into a trailing commit, so every staged hunk lands exactly once.
+pub fn normalize_split_plan(
+    plan: SplitPlan,
+    hunks: &[Hunk],
+    diff: &DiffSummary,
+    symbols: &[SymbolChange],
+    style: MessageStyle,
+) -> SplitPlan {
+    let mut assigned = HashSet::new();
+    let mut commits = Vec::new();
+
+    for group in plan.commits {
+        let mut ids = Vec::new();
+        for id in &group.hunks {
+            for hunk in hunks.iter().filter(|h| &h.id == id || &h.path == id) {
+                if assigned.insert(hunk.id.clone()) {
+                    ids.push(hunk.id.clone());
+                }
+            }
+        }
+
+        let message = group.message.trim().to_string();
+        if !ids.is_empty() && !message.is_empty() {
+            commits.push(SplitGroup { message, hunks: ids });
+        }
+    }
+
+    let leftover: Vec<&Hunk> = hunks.iter().filter(|h| !assigned.contains(&h.id)).collect();
+    if !leftover.is_empty() {
+        commits.push(group_for(&leftover, diff, symbols, style));
+    }
+
+    SplitPlan { commits }
+}
+
+/// Offline plan: one commit per top-level directory
+pub fn fallback_split_plan(hunks: &[Hunk], diff: &DiffSummary, symbols: &[SymbolChange], style: MessageStyle) -> SplitPlan {
+    let mut by_dir: BTreeMap<&str, Vec<&Hunk>> = BTreeMap::new();
+    for hunk in hunks {
+        let dir = match hunk.path.split_once('/') {
+            Some((dir, _)) => dir,
+            None => ".",
+        };
+        by_dir.entry(dir).or_default().push(hunk);
+    }
+
+    SplitPlan {
+        commits: by_dir.values().map(|hunks| group_for(hunks, diff, symbols, style)).collect(),
+    }
+}
+
+fn group_for(hunks: &[&Hunk], diff: &DiffSummary, symbols: &[SymbolChange], style: MessageStyle) -> SplitGroup {
+    let in_group = |path: &str| hunks.iter().any(|h| h.path == path);
+    let files = diff.files.iter().filter(|f| in_group(&f.path)).cloned().collect();
+    let symbols: Vec<SymbolChange> = symbols.iter().filter(|s| in_group(&s.path)).cloned().collect();
+
+    SplitGroup {
+        message: generate_fallback_message(&DiffSummary::from_files(files), &symbols, style),
+        hunks: hunks.iter().map(|h| h.id.clone()).collect(),
+    }
+}
+
+/// Creates one commit per group on top of HEAD, through `perform_git_commit`
+/// so the backend and hooks apply to each. Each commit's tree is HEAD's tree
+/// plus the group's hunks, the last one matches the index. Groups that change
+/// nothing are skipped. If a commit fails, the ones before it stay and the
+/// index keeps the remaining changes staged.
+pub fn create_split_commits<'p>(
+    repo: &Repository,
+    plan: &'p SplitPlan,
+    hunks: &[Hunk],
+    options: &CommitOptions,
+) -> Result<Vec<(Oid, &'p SplitGroup)>> {
+    let by_id: HashMap<&str, &Hunk> = hunks.iter().map(|h| (h.id.as_str(), h)).collect();
+    let index_tree = repo.find_tree(repo.index()?.write_tree()?)?;
+    let head_tree = repo.head()?.peel_to_tree()?;
+
+    // Build every tree before touching HEAD so a bad plan leaves the branch alone
+    let mut steps = Vec::new();
+    let mut tree = head_tree.clone();
+    for group in &plan.commits {
+        let selected: Vec<&Hunk> = group
+            .hunks
+            .iter()
+            .filter_map(|id| by_id.get(id.as_str()).copied())
+            .collect();
+
+        let next = apply_selected(repo, &tree, &index_tree, &selected)?;
+        if next.id() != tree.id() {
+            steps.push((group, next.id()));
+        }
+        tree = next;
+    }
+
+    if tree.id() != index_tree.id() {
+        return Err(CommitError::GitOperationError(
+            "split plan does not cover all staged changes".to_string(),
+        ));
+    }
+
+    // The commit backends commit the index, stage each step's tree in turn
+    let options = CommitOptions { stage_all: false, ..options.clone() };
+    let mut oids = Vec::new();
+    for (group, tree_id) in steps {
+        let committed = stage_tree(repo, tree_id).and_then(|_| perform_git_commit(repo, &group.message, &options));
+        match committed {
+            Ok(oid) => oids.push((oid, group)),
+            Err(e) => {
+                stage_tree(repo, index_tree.id())?;
+                return Err(e);
+            }
+        }
+    }
+
+    Ok(oids)
+}
+
+fn stage_tree(repo: &Repository, tree_id: Oid) -> Result<()> {
+    let mut index = repo.index()?;
+    index.read_tree(&repo.find_tree(tree_id)?)?;
+    index.write()?;
+    Ok(())
+}
+
+fn apply_selected<'r>(
+    repo: &'r Repository,
+    base: &Tree<'r>,
+    target: &Tree<'r>,
+    selected: &[&Hunk],
+) -> Result<Tree<'r>> {
+    // Re-diff against the partial tree, earlier commits shift line numbers.
+    // Limit it to the group's files, libgit2 applies every deletion in a diff
+    // regardless of what the delta callback says.
+    let selection = HunkSelection::new(selected);
+    let paths = selection.paths();
+    if paths.is_empty() {
+        return Ok(base.clone());
+    }
+    let diff = diff_trees(repo, base, target, &paths)?;
+
+    let mut apply_options = selection.apply_options();
+    let mut index = repo.apply_to_tree(base, &diff, Some(&mut apply_options))?;
+    Ok(repo.find_tree(index.write_tree_to(repo)?)?)
+}
+
+fn diff_trees<'r>(repo: &'r Repository, old: &Tree<'r>, new: &Tree<'r>, paths: &[&str]) -> Result<Diff<'r>> {
+    let mut opts = DiffOptions::new();
+    opts.show_binary(true).disable_pathspec_match(true);
+    for path in paths {
+        opts.pathspec(path);
+    }
+    Ok(repo.diff_tree_to_tree(Some(old), Some(new), Some(&mut opts))?)
+}
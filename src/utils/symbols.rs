@@ -0,0 +1,325 @@
+use git2::{Oid, Repository};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use tree_sitter::{Language, Node, Parser};
+use crate::utils::diff::{DiffSummary, FileDiff, FileStatus};
+
+// Parsing megabyte-sized files isn't worth it for a commit message
+const MAX_SOURCE_BYTES: usize = 512 * 1024;
+
+// Keeps a sweeping refactor's symbol list from crowding out the diff
+const MAX_PROMPT_FILES: usize = 30;
+const MAX_PROMPT_ENTRIES_PER_FILE: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Lang {
+    Rust,
+    Python,
+    TypeScript,
+    Tsx,
+    Go,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SymbolChange {
+    pub path: String,
+    /// Keyword as written in the language: `fn`, `struct`, `class`, `func`...
+    pub kind: &'static str,
+    pub name: String,
+    pub change: ChangeKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase", tag = "type")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+    Renamed { from: String },
+}
+
+struct Symbol {
+    kind: &'static str,
+    name: String,
+    text: String,
+    /// The definition with its name cut out, equal for a pure rename
+    unnamed_text: String,
+}
+
+impl std::fmt::Display for SymbolChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.change {
+            ChangeKind::Added => write!(f, "added {} {}", self.kind, self.name),
+            ChangeKind::Removed => write!(f, "removed {} {}", self.kind, self.name),
+            ChangeKind::Modified => write!(f, "modified {} {}", self.kind, self.name),
+            ChangeKind::Renamed { from } => write!(f, "renamed {} {} to {}", self.kind, from, self.name),
+        }
+    }
+}
+
+/// Parses both sides of every supported source file and lists the
+/// functions, types and traits that were added, removed, changed or renamed
+pub fn summarize_symbols(repo: &Repository, diff: &DiffSummary) -> Vec<SymbolChange> {
+    let mut changes = Vec::new();
+
+    for file in &diff.files {
+        let lang = match language_for(&file.path) {
+            Some(lang) if file.excluded.is_none() => lang,
+            _ => continue,
+        };
+
+        let old_source = match file.status {
+            FileStatus::Added => Some(String::new()),
+            _ => file.old_id.as_deref().and_then(|id| load_blob(repo, id)),
+        };
+        let new_source = match file.status {
+            FileStatus::Deleted => Some(String::new()),
+            _ => load_new_side(repo, file),
+        };
+
+        if let (Some(old_source), Some(new_source)) = (old_source, new_source) {
+            if let (Some(old), Some(new)) = (parse_symbols(lang, &old_source), parse_symbols(lang, &new_source)) {
+                changes.extend(compare_symbols(&file.path, old, new));
+            }
+        }
+    }
+
+    changes
+}
+
+/// One line per file, e.g. `src/config.rs: renamed fn parse_config to load_config`,
+/// capped in files and entries per file
+pub fn symbols_prompt_text(changes: &[SymbolChange]) -> String {
+    let mut files: Vec<(String, Vec<String>)> = Vec::new();
+
+    for change in changes {
+        match files.iter_mut().find(|(path, _)| *path == change.path) {
+            Some((_, entries)) => entries.push(change.to_string()),
+            None => files.push((change.path.clone(), vec![change.to_string()])),
+        }
+    }
+
+    let mut lines: Vec<String> = files
+        .iter()
+        .take(MAX_PROMPT_FILES)
+        .map(|(path, entries)| {
+            let mut shown = entries[..entries.len().min(MAX_PROMPT_ENTRIES_PER_FILE)].join(", ");
+            if entries.len() > MAX_PROMPT_ENTRIES_PER_FILE {
+                shown.push_str(&format!(" and {} more", entries.len() - MAX_PROMPT_ENTRIES_PER_FILE));
+            }
+            format!("{}: {}", path, shown)
+        })
+        .collect();
+    if files.len() > MAX_PROMPT_FILES {
+        lines.push(format!("... and {} more files", files.len() - MAX_PROMPT_FILES));
+    }
+
+    lines.join("\n")
+}
+
+fn language_for(path: &str) -> Option<Lang> {
+    match path.rsplit_once('.')?.1 {
+        "rs" => Some(Lang::Rust),
+        "py" => Some(Lang::Python),
+        "ts" | "mts" | "cts" => Some(Lang::TypeScript),
+        "tsx" => Some(Lang::Tsx),
+        "go" => Some(Lang::Go),
+        _ => None,
+    }
+}
+
+fn grammar(lang: Lang) -> Language {
+    match lang {
+        Lang::Rust => tree_sitter_rust::LANGUAGE.into(),
+        Lang::Python => tree_sitter_python::LANGUAGE.into(),
+        Lang::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+        Lang::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+        Lang::Go => tree_sitter_go::LANGUAGE.into(),
+    }
+}
+
+fn load_blob(repo: &Repository, id: &str) -> Option<String> {
+    let blob = repo.find_blob(Oid::from_str(id).ok()?).ok()?;
+    if blob.size() > MAX_SOURCE_BYTES {
+        return None;
+    }
+    String::from_utf8(blob.content().to_vec()).ok()
+}
+
+fn load_new_side(repo: &Repository, file: &FileDiff) -> Option<String> {
+    // Workdir content isn't in the object database, read it from disk instead
+    if let Some(source) = file.new_id.as_deref().and_then(|id| load_blob(repo, id)) {
+        return Some(source);
+    }
+
+    let path = repo.workdir()?.join(&file.path);
+    if fs::metadata(&path).ok()?.len() as usize > MAX_SOURCE_BYTES {
+        return None;
+    }
+    fs::read_to_string(path).ok()
+}
+
+fn parse_symbols(lang: Lang, source: &str) -> Option<Vec<Symbol>> {
+    let mut parser = Parser::new();
+    parser.set_language(&grammar(lang)).ok()?;
+    let tree = parser.parse(source, None)?;
+
+    let mut symbols = Vec::new();
+    collect_symbols(lang, tree.root_node(), source, None, &mut symbols);
+    Some(symbols)
+}
+
+fn collect_symbols(lang: Lang, node: Node, source: &str, scope: Option<&str>, out: &mut Vec<Symbol>) {
+    let text = |node: Node| source[node.byte_range()].to_string();
+    let field = |name: &str| node.child_by_field_name(name).map(text);
+
+    let (definition, child_scope) = match (lang, node.kind()) {
+        (Lang::Rust, "function_item" | "function_signature_item") => (Some(("fn", field("name"))), None),
+        (Lang::Rust, "struct_item") => (Some(("struct", field("name"))), None),
+        (Lang::Rust, "enum_item") => (Some(("enum", field("name"))), None),
+        (Lang::Rust, "type_item") => (Some(("type", field("name"))), None),
+        (Lang::Rust, "trait_item") => (Some(("trait", field("name"))), field("name")),
+        (Lang::Rust, "mod_item") => (Some(("mod", field("name"))), field("name")),
+        (Lang::Rust, "impl_item") => {
+            // `impl<T> Foo<T>` scopes its methods to Foo
+            let type_name = field("type").map(|t| t.split('<').next().unwrap_or_default().trim().to_string());
+            (None, type_name)
+        }
+
+        (Lang::Python, "function_definition") => (Some(("def", field("name"))), None),
+        (Lang::Python, "class_definition") => (Some(("class", field("name"))), field("name")),
+
+        (Lang::TypeScript | Lang::Tsx, "function_declaration") => (Some(("function", field("name"))), None),
+        (Lang::TypeScript | Lang::Tsx, "class_declaration" | "abstract_class_declaration") => {
+            (Some(("class", field("name"))), field("name"))
+        }
+        (Lang::TypeScript | Lang::Tsx, "method_definition") => (Some(("method", field("name"))), None),
+        (Lang::TypeScript | Lang::Tsx, "interface_declaration") => (Some(("interface", field("name"))), None),
+        (Lang::TypeScript | Lang::Tsx, "type_alias_declaration") => (Some(("type", field("name"))), None),
+        (Lang::TypeScript | Lang::Tsx, "enum_declaration") => (Some(("enum", field("name"))), None),
+        (Lang::TypeScript | Lang::Tsx, "variable_declarator")
+            if node
+                .child_by_field_name("value")
+                .is_some_and(|v| matches!(v.kind(), "arrow_function" | "function_expression")) =>
+        {
+            (Some(("function", field("name"))), None)
+        }
+
+        (Lang::Go, "function_declaration") => (Some(("func", field("name"))), None),
+        (Lang::Go, "method_declaration") => {
+            // `func (s *Server) Start()` becomes Server.Start
+            let receiver = field("receiver").and_then(|r| {
+                r.trim_matches(|c| c == '(' || c == ')')
+                    .split_whitespace()
+                    .last()
+                    .map(|t| t.trim_start_matches('*').to_string())
+            });
+            let name = field("name").map(|name| match receiver {
+                Some(receiver) => format!("{}.{}", receiver, name),
+                None => name,
+            });
+            (Some(("func", name)), None)
+        }
+        (Lang::Go, "type_spec") => {
+            let kind = match node.child_by_field_name("type").map(|t| t.kind()) {
+                Some("struct_type") => "struct",
+                Some("interface_type") => "interface",
+                _ => "type",
+            };
+            (Some((kind, field("name"))), None)
+        }
+        _ => (None, None),
+    };
+
+    if let Some((kind, Some(short_name))) = definition {
+        let separator = if lang == Lang::Rust { "::" } else { "." };
+        let name = match scope {
+            Some(scope) if !short_name.contains('.') => format!("{}{}{}", scope, separator, short_name),
+            _ => short_name,
+        };
+        let unnamed_text = match node.child_by_field_name("name") {
+            Some(name_node) => format!(
+                "{}{}",
+                &source[node.start_byte()..name_node.start_byte()],
+                &source[name_node.end_byte()..node.end_byte()]
+            ),
+            None => text(node),
+        };
+        out.push(Symbol {
+            kind,
+            name,
+            text: text(node),
+            unnamed_text,
+        });
+    }
+
+    let scope = child_scope.as_deref().or(scope);
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_symbols(lang, child, source, scope, out);
+    }
+}
+
+fn compare_symbols(path: &str, old: Vec<Symbol>, new: Vec<Symbol>) -> Vec<SymbolChange> {
+    let key = |s: &Symbol| (s.kind, s.name.clone());
+    let old_by_key: HashMap<_, &Symbol> = old.iter().map(|s| (key(s), s)).collect();
+    let new_by_key: HashMap<_, &Symbol> = new.iter().map(|s| (key(s), s)).collect();
+
+    let change = |symbol: &Symbol, change: ChangeKind| SymbolChange {
+        path: path.to_string(),
+        kind: symbol.kind,
+        name: symbol.name.clone(),
+        change,
+    };
+
+    let mut removed: Vec<&Symbol> = old.iter().filter(|s| !new_by_key.contains_key(&key(s))).collect();
+    let mut changes = Vec::new();
+
+    for symbol in &new {
+        match old_by_key.get(&key(symbol)) {
+            Some(previous) if previous.text != symbol.text => changes.push(change(symbol, ChangeKind::Modified)),
+            Some(_) => {}
+            None => {
+                // Same kind and same body apart from the name: it was renamed
+                let renamed_from = removed
+                    .iter()
+                    .position(|old| old.kind == symbol.kind && old.unnamed_text == symbol.unnamed_text);
+                match renamed_from {
+                    Some(i) => {
+                        let from = removed.remove(i).name.clone();
+                        changes.push(change(symbol, ChangeKind::Renamed { from }));
+                    }
+                    None => changes.push(change(symbol, ChangeKind::Added)),
+                }
+            }
+        }
+    }
+
+    changes.extend(removed.into_iter().map(|symbol| change(symbol, ChangeKind::Removed)));
+
+    // A class or trait whose change is already explained by its members: the
+    // same once every member is cut out. Go methods live outside their type.
+    let outline = |symbols: &[Symbol], container: &Symbol| {
+        symbols
+            .iter()
+            .filter(|s| is_member(&s.name, &container.name))
+            .fold(container.text.clone(), |text, member| text.replacen(&member.text, "", 1))
+            .split_whitespace()
+            .collect::<String>()
+    };
+    changes.retain(|c| {
+        let key = (c.kind, c.name.clone());
+        match (c.change == ChangeKind::Modified, old_by_key.get(&key), new_by_key.get(&key)) {
+            (true, Some(previous), Some(current)) => outline(&old, previous) != outline(&new, current),
+            _ => true,
+        }
+    });
+
+    changes
+}
+
+fn is_member(name: &str, container: &str) -> bool {
+    name.strip_prefix(container)
+        .is_some_and(|rest| rest.starts_with("::") || rest.starts_with('.'))
+}
@@ -0,0 +1,143 @@
+use rusty_commit::utils::budget::{allocate, budget_diff, split_into_chunks, DiffBudget};
+use rusty_commit::utils::diff::{DiffLine, DiffSummary, FileDiff, FileStatus, HunkDiff};
+
+/// A new file with `hunks` hunks of `lines` added lines each
+fn added(path: &str, hunks: usize, lines: usize) -> FileDiff {
+    let hunks: Vec<HunkDiff> = (0..hunks)
+        .map(|h| {
+            let start = (h * (lines + 10) + 1) as u32;
+            HunkDiff {
+                header: format!("@@ -{},0 +{},{} @@", start, start, lines),
+                old_start: start,
+                old_lines: 0,
+                new_start: start,
+                new_lines: lines as u32,
+                lines: (0..lines)
+                    .map(|n| DiffLine { origin: '+', content: format!("let value_{} = compute({});\n", n, n) })
+                    .collect(),
+            }
+        })
+        .collect();
+
+    FileDiff {
+        path: path.to_string(),
+        old_path: None,
+        status: FileStatus::Added,
+        old_mode: 0,
+        new_mode: 0o100644,
+        binary: false,
+        old_size: 0,
+        new_size: 0,
+        old_id: None,
+        new_id: None,
+        excluded: None,
+        additions: hunks.len() * lines,
+        deletions: 0,
+        hunks,
+    }
+}
+
+fn budget(max_tokens: usize, max_lines: usize) -> DiffBudget {
+    let mut budget = DiffBudget::for_model("openai/gpt-4o", 500, 0);
+    budget.max_tokens = max_tokens;
+    budget.max_lines = max_lines;
+    budget
+}
+
+/// The rendered lines belonging to `path`, header included
+fn section<'a>(out: &'a str, path: &str) -> Vec<&'a str> {
+    out.lines()
+        .skip_while(|line| !line.starts_with(&format!("{} (", path)))
+        .enumerate()
+        .take_while(|(i, line)| *i == 0 || !line.contains(" (added"))
+        .map(|(_, line)| line)
+        .collect()
+}
+
+#[test]
+fn small_files_get_what_they_need_and_big_ones_share_the_rest() {
+    assert_eq!(allocate(&[false, false, false], &[10, 100, 100], 110), [10, 50, 50]);
+    assert_eq!(allocate(&[false, false], &[10, 20], 1000), [10, 20]);
+    assert_eq!(allocate(&[false, false], &[100, 100], 0), [0, 0]);
+}
+
+#[test]
+fn generated_files_only_get_the_leftovers() {
+    assert_eq!(allocate(&[true, false], &[80, 80], 100), [20, 80]);
+    assert_eq!(allocate(&[true, false], &[80, 10], 100), [80, 10]);
+}
+
+#[test]
+fn allocation_never_exceeds_what_is_available() {
+    let costs = [0, 1, 7, 33, 120, 500, 999];
+    let generated = [false, true, false, true, false, false, true];
+    for available in [0, 1, 5, 50, 300, 1000, 5000] {
+        let allocation = allocate(&generated, &costs, available);
+        assert!(allocation.iter().sum::<usize>() <= available);
+        assert!(allocation.iter().zip(&costs).all(|(grant, cost)| grant <= cost));
+    }
+}
+
+#[test]
+fn every_file_keeps_its_header_and_a_note_on_what_was_cut() {
+    let summary = DiffSummary::from_files(vec![
+        added("src/small.rs", 1, 3),
+        added("src/big.rs", 4, 40),
+    ]);
+    let out = budget_diff(&summary, &budget(400, 1000));
+
+    let small = section(&out, "src/small.rs");
+    assert_eq!(small.len(), 1 + 1 + 3, "{}", out);
+
+    let big = section(&out, "src/big.rs");
+    let note = big.last().unwrap();
+    assert!(note.starts_with("... hunk truncated") || note.contains("omitted"), "{}", out);
+}
+
+#[test]
+fn generated_files_are_cut_first() {
+    let summary = DiffSummary::from_files(vec![
+        added("dist/app.min.js", 1, 60),
+        added("src/app.rs", 1, 60),
+    ]);
+    let out = budget_diff(&summary, &budget(1000, 100));
+
+    assert!(!section(&out, "src/app.rs").iter().any(|line| line.starts_with("...")), "{}", out);
+    assert!(section(&out, "dist/app.min.js").last().unwrap().starts_with("..."), "{}", out);
+}
+
+#[test]
+fn budget_is_never_exceeded() {
+    let summary = DiffSummary::from_files(vec![
+        added("src/a.rs", 1, 5),
+        added("src/b.rs", 3, 50),
+        added("src/c.rs", 1, 400),
+        added("target/gen.rs", 2, 100),
+    ]);
+
+    for (max_tokens, max_lines) in [(300, 40), (800, 120), (2_000, 300), (5_000, 2_000), (100_000, 100)] {
+        let budget = budget(max_tokens, max_lines);
+        let out = budget_diff(&summary, &budget);
+        assert!(budget.estimate_tokens(&out) <= max_tokens, "{} tokens over {}:\n{}", budget.estimate_tokens(&out), max_tokens, out);
+        assert!(out.lines().count() <= max_lines, "{} lines over {}:\n{}", out.lines().count(), max_lines, out);
+    }
+}
+
+#[test]
+fn oversized_diffs_split_into_chunks_that_fit() {
+    let summary = DiffSummary::from_files((0..6).map(|i| added(&format!("src/f{}.rs", i), 1, 40)).collect());
+    let budget = budget(1_000, 10_000);
+    let chunks = split_into_chunks(&summary, &budget);
+
+    assert!(chunks.len() > 1);
+    let paths: Vec<String> = chunks.iter().flat_map(|chunk| chunk.paths()).collect();
+    assert_eq!(paths, summary.paths());
+    for chunk in &chunks {
+        assert!(chunk.files.len() == 1 || budget.estimate_tokens(&chunk.to_prompt_text()) <= budget.max_tokens);
+    }
+
+    // A file bigger than the budget stands alone
+    let summary = DiffSummary::from_files(vec![added("a.rs", 1, 5), added("huge.rs", 1, 400), added("b.rs", 1, 5)]);
+    let chunks = split_into_chunks(&summary, &budget);
+    assert_eq!(chunks.iter().map(|c| c.paths()).collect::<Vec<_>>(), [vec!["a.rs"], vec!["huge.rs"], vec!["b.rs"]]);
+}
@@ -0,0 +1,78 @@
+use rusty_commit::api::cache::ResponseCache;
+use rusty_commit::config::CacheConfig;
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tempfile::TempDir;
+
+fn cache(ttl_hours: u64, max_size_mb: u64) -> (TempDir, ResponseCache) {
+    let dir = tempfile::tempdir().unwrap();
+    let config = CacheConfig { enabled: true, ttl_hours, max_size_mb };
+    let cache = ResponseCache::in_dir(dir.path().join("responses"), &config);
+    (dir, cache)
+}
+
+/// Writes an entry as if it had been stored `age` seconds ago
+fn write_aged(dir: &TempDir, key: &str, age: u64) {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let entry = serde_json::json!({ "created_at": now - age, "response": "feat: old" });
+    fs::write(dir.path().join("responses").join(format!("{}.json", key)), entry.to_string()).unwrap();
+}
+
+#[test]
+fn key_covers_every_part_and_their_boundaries() {
+    let key = ResponseCache::key(&["model", "prompt"]);
+    assert_eq!(key, ResponseCache::key(&["model", "prompt"]));
+    assert_eq!(key.len(), 64);
+
+    assert_ne!(key, ResponseCache::key(&["model", "prompt2"]));
+    assert_ne!(key, ResponseCache::key(&["prompt", "model"]));
+    assert_ne!(ResponseCache::key(&["ab", "c"]), ResponseCache::key(&["a", "bc"]));
+    assert_ne!(ResponseCache::key(&["a"]), ResponseCache::key(&["a", ""]));
+}
+
+#[test]
+fn stored_responses_come_back() {
+    let (_dir, cache) = cache(1, 1);
+    assert_eq!(cache.get("missing"), None);
+
+    cache.put("abc", "feat: add cache").unwrap();
+    assert_eq!(cache.get("abc").as_deref(), Some("feat: add cache"));
+}
+
+#[test]
+fn expired_entries_are_dropped() {
+    let (dir, cache) = cache(1, 1);
+    cache.put("fresh", "feat: new").unwrap();
+    write_aged(&dir, "stale", 2 * 60 * 60);
+    write_aged(&dir, "recent", 30 * 60);
+
+    assert_eq!(cache.get("stale"), None);
+    assert!(!dir.path().join("responses/stale.json").exists());
+    assert_eq!(cache.get("recent").as_deref(), Some("feat: old"));
+    assert_eq!(cache.get("fresh").as_deref(), Some("feat: new"));
+}
+
+#[test]
+fn huge_ttl_does_not_overflow() {
+    let (dir, cache) = cache(u64::MAX, u64::MAX);
+    cache.put("fresh", "feat: new").unwrap();
+    write_aged(&dir, "ancient", 40 * 365 * 24 * 60 * 60);
+
+    assert_eq!(cache.get("ancient").as_deref(), Some("feat: old"));
+    assert_eq!(cache.get("fresh").as_deref(), Some("feat: new"));
+}
+
+#[test]
+fn oldest_entries_are_evicted_past_the_size_limit() {
+    let (_dir, cache) = cache(1, 1);
+    let big = "x".repeat(400 * 1024);
+
+    for key in ["first", "second", "third"] {
+        cache.put(key, &big).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+
+    assert_eq!(cache.get("first"), None);
+    assert!(cache.get("second").is_some());
+    assert!(cache.get("third").is_some());
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use common::TestRepo;
+use rusty_commit::utils::diff::FileStatus;
+use rusty_commit::utils::git::get_git_changes;
+
+fn body(lines: usize) -> String {
+    (1..=lines).map(|n| format!("fn line_{}() {{}}\n", n)).collect()
+}
+
+fn committed(files: &[(&str, &str)]) -> TestRepo {
+    let test = TestRepo::new();
+    for (path, content) in files {
+        test.write(path, content);
+    }
+    test.stage_all();
+    test.commit("add files");
+    test
+}
+
+#[test]
+fn staged_rename_is_one_file_under_its_new_path() {
+    let test = committed(&[("src/old.rs", &body(20))]);
+    test.remove("src/old.rs");
+    test.write("src/new.rs", &body(20).replace("line_20", "line_twenty"));
+    test.stage_all();
+
+    let changes = get_git_changes(&test.repo).unwrap();
+    assert_eq!(changes.staged_files, ["src/new.rs"]);
+    let file = &changes.staged_diff.files[0];
+    assert_eq!(file.status, FileStatus::Renamed);
+    assert_eq!(file.old_path.as_deref(), Some("src/old.rs"));
+    assert_eq!(file.prompt_header(), "src/old.rs -> src/new.rs (renamed, +1 -1)");
+}
+
+#[test]
+fn plain_mv_shows_up_as_an_unstaged_rename() {
+    let test = committed(&[("a.txt", &body(20))]);
+    std::fs::rename(test.path().join("a.txt"), test.path().join("b.txt")).unwrap();
+
+    let changes = get_git_changes(&test.repo).unwrap();
+    assert!(changes.staged_files.is_empty());
+    let file = &changes.unstaged_diff.files[0];
+    assert_eq!(changes.unstaged_diff.files.len(), 1, "{:?}", changes.unstaged_diff.paths());
+    assert_eq!((file.status, file.old_path.as_deref(), file.path.as_str()), (FileStatus::Renamed, Some("a.txt"), "b.txt"));
+}
+
+#[test]
+fn copies_of_modified_files_are_detected() {
+    let test = committed(&[("a.txt", &body(20))]);
+    test.write("a.txt", &format!("{}fn extra() {{}}\n", body(20)));
+    test.write("b.txt", &body(20));
+    test.stage_all();
+
+    let changes = get_git_changes(&test.repo).unwrap();
+    let copy = changes.staged_diff.files.iter().find(|f| f.path == "b.txt").unwrap();
+    assert_eq!((copy.status, copy.old_path.as_deref()), (FileStatus::Copied, Some("a.txt")));
+    assert_eq!(copy.additions + copy.deletions, 0);
+}
+
+#[test]
+fn untracked_files_are_part_of_unstaged_changes() {
+    let test = TestRepo::new();
+    test.write("notes/todo.md", "- write tests\n");
+
+    let changes = get_git_changes(&test.repo).unwrap();
+    assert_eq!(changes.unstaged_files, ["notes/todo.md"]);
+    let file = &changes.unstaged_diff.files[0];
+    assert_eq!((file.status, file.additions), (FileStatus::Added, 1));
+}
+
+#[cfg(unix)]
+#[test]
+fn mode_changes_are_described() {
+    let test = committed(&[("run.sh", "echo hi\n")]);
+    common::make_executable(&test.path().join("run.sh"));
+
+    let changes = get_git_changes(&test.repo).unwrap();
+    let file = &changes.unstaged_diff.files[0];
+    assert_eq!((file.old_mode, file.new_mode), (0o100644, 0o100755));
+    assert_eq!(file.mode_change().as_deref(), Some("made executable"));
+    assert_eq!(file.prompt_header(), "run.sh (modified, made executable, +0 -0)");
+}
+
+#[cfg(unix)]
+#[test]
+fn file_turned_symlink_is_a_type_change() {
+    let test = committed(&[("link", "target\n")]);
+    test.remove("link");
+    std::os::unix::fs::symlink("README.md", test.path().join("link")).unwrap();
+    test.stage_all();
+
+    let changes = get_git_changes(&test.repo).unwrap();
+    let file = &changes.staged_diff.files[0];
+    assert_eq!(file.status, FileStatus::Typechange);
+    assert_eq!(file.mode_change().as_deref(), Some("file -> symlink"));
+}
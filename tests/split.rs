@@ -0,0 +1,164 @@
+mod common;
+
+use common::{numbered, TestRepo};
+use rusty_commit::config::CommitBackend;
+use rusty_commit::utils::git::CommitOptions;
+use rusty_commit::utils::split::{collect_staged_hunks, create_split_commits, SplitGroup, SplitPlan};
+
+fn plan(groups: &[(&str, &[&str])]) -> SplitPlan {
+    SplitPlan {
+        commits: groups
+            .iter()
+            .map(|(message, hunks)| SplitGroup {
+                message: message.to_string(),
+                hunks: hunks.iter().map(|id| id.to_string()).collect(),
+            })
+            .collect(),
+    }
+}
+
+fn staged_hunks(test: &TestRepo) -> Vec<rusty_commit::utils::hunks::Hunk> {
+    collect_staged_hunks(&test.repo).unwrap()
+}
+
+#[test]
+fn identical_hunks_in_one_file_land_in_their_own_commits() {
+    let test = TestRepo::new();
+    test.write("a.txt", &numbered("x", "x"));
+    test.stage(&["a.txt"]);
+    test.commit("add a");
+
+    // Both hunks are "-x +y", only their position tells them apart
+    test.write("a.txt", &numbered("y", "y"));
+    test.stage(&["a.txt"]);
+    let hunks = staged_hunks(&test);
+    assert_eq!(hunks.len(), 2);
+
+    let plan = plan(&[("fix: second", &["a.txt#2"]), ("fix: first", &["a.txt#1"])]);
+    let commits = create_split_commits(&test.repo, &plan, &hunks, &CommitOptions::default()).unwrap();
+    assert_eq!(commits.len(), 2);
+
+    let first = test.repo.find_commit(commits[0].0).unwrap();
+    assert_eq!(first.summary(), Some("fix: second"));
+    let blob = first.tree().unwrap().get_path("a.txt".as_ref()).unwrap().to_object(&test.repo).unwrap();
+    assert_eq!(std::str::from_utf8(blob.as_blob().unwrap().content()).unwrap(), numbered("x", "y"));
+
+    assert_eq!(test.head_message(), "fix: first");
+    assert_eq!(test.head_file("a.txt").unwrap(), numbered("y", "y"));
+}
+
+#[test]
+fn groups_split_files_and_the_index_stays_in_place() {
+    let test = TestRepo::new();
+    test.write("src/a.rs", "fn a() {}\n");
+    test.write("docs/b.md", "# b\n");
+    test.stage(&["src/a.rs", "docs/b.md"]);
+    let hunks = staged_hunks(&test);
+
+    let plan = plan(&[("feat: add a", &["src/a.rs#1"]), ("docs: add b", &["docs/b.md#1"])]);
+    create_split_commits(&test.repo, &plan, &hunks, &CommitOptions::default()).unwrap();
+
+    assert_eq!(test.commit_count(), 3);
+    let parent = test.repo.head().unwrap().peel_to_commit().unwrap().parent(0).unwrap();
+    assert!(parent.tree().unwrap().get_path("docs/b.md".as_ref()).is_err());
+    assert!(test.git(&["status", "--porcelain"]).is_empty());
+}
+
+#[test]
+fn groups_that_change_nothing_are_skipped() {
+    let test = TestRepo::new();
+    test.write("a.txt", "a\n");
+    test.stage(&["a.txt"]);
+    let hunks = staged_hunks(&test);
+
+    let plan = plan(&[("chore: nothing", &["missing#1"]), ("feat: add a", &["a.txt#1"])]);
+    let commits = create_split_commits(&test.repo, &plan, &hunks, &CommitOptions::default()).unwrap();
+
+    assert_eq!(commits.len(), 1);
+    assert_eq!(commits[0].1.message, "feat: add a");
+    assert_eq!(test.commit_count(), 2);
+}
+
+#[test]
+fn incomplete_plan_leaves_head_alone() {
+    let test = TestRepo::new();
+    test.write("a.txt", "a\n");
+    test.write("b.txt", "b\n");
+    test.stage(&["a.txt", "b.txt"]);
+    let hunks = staged_hunks(&test);
+
+    let plan = plan(&[("feat: add a", &["a.txt#1"])]);
+    assert!(create_split_commits(&test.repo, &plan, &hunks, &CommitOptions::default()).is_err());
+    assert_eq!(test.commit_count(), 1);
+}
+
+#[test]
+fn split_commits_go_through_the_cli_backend() {
+    let test = TestRepo::new();
+    test.write("a.txt", "a\n");
+    test.write("b.txt", "b\n");
+    test.stage(&["a.txt", "b.txt"]);
+    let hunks = staged_hunks(&test);
+
+    let plan = plan(&[("feat: add a", &["a.txt#1"]), ("feat: add b", &["b.txt#1"])]);
+    let options = CommitOptions { backend: CommitBackend::Cli, ..Default::default() };
+    create_split_commits(&test.repo, &plan, &hunks, &options).unwrap();
+
+    assert_eq!(test.git(&["log", "--format=%s"]), "feat: add b\nfeat: add a\ninitial commit\n");
+    assert!(test.git(&["status", "--porcelain"]).is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn hooks_run_for_every_split_commit_unless_no_verify() {
+    let test = TestRepo::new();
+    test.hook("commit-msg", "#!/bin/sh\necho 'Checked' >> \"$1\"\n");
+    test.write("a.txt", "a\n");
+    test.write("b.txt", "b\n");
+    test.stage(&["a.txt", "b.txt"]);
+    let hunks = staged_hunks(&test);
+    let plan = plan(&[("feat: add a", &["a.txt#1"]), ("feat: add b", &["b.txt#1"])]);
+
+    create_split_commits(&test.repo, &plan, &hunks, &CommitOptions::default()).unwrap();
+    assert_eq!(test.git(&["log", "-2", "--format=%B"]).matches("Checked").count(), 2);
+
+    // A failing pre-commit stops the split and keeps everything staged
+    let test = TestRepo::new();
+    test.hook("pre-commit", "#!/bin/sh\nexit 1\n");
+    test.write("a.txt", "a\n");
+    test.write("b.txt", "b\n");
+    test.stage(&["a.txt", "b.txt"]);
+    let hunks = staged_hunks(&test);
+
+    assert!(create_split_commits(&test.repo, &plan, &hunks, &CommitOptions::default()).is_err());
+    assert_eq!(test.commit_count(), 1);
+    assert_eq!(test.git(&["diff", "--cached", "--name-only"]), "a.txt\nb.txt\n");
+
+    let options = CommitOptions { no_verify: true, ..Default::default() };
+    create_split_commits(&test.repo, &plan, &hunks, &options).unwrap();
+    assert_eq!(test.commit_count(), 3);
+}
+
+#[test]
+fn staged_renames_are_split_with_their_old_path() {
+    let test = TestRepo::new();
+    test.write("old.txt", &numbered("a", "b"));
+    test.stage(&["old.txt"]);
+    test.commit("add old");
+
+    test.write("b.txt", "b\n");
+    test.git(&["mv", "old.txt", "new.txt"]);
+    test.git(&["add", "b.txt"]);
+    // git2 keeps the index it loaded, pick up what the CLI staged
+    test.repo.index().unwrap().read(true).unwrap();
+    let hunks = staged_hunks(&test);
+    assert!(hunks.iter().any(|h| h.path == "old.txt"));
+
+    let plan = plan(&[("refactor: rename old.txt", &["old.txt#1", "new.txt#1"]), ("feat: add b", &["b.txt#1"])]);
+    create_split_commits(&test.repo, &plan, &hunks, &CommitOptions::default()).unwrap();
+
+    assert_eq!(test.commit_count(), 4);
+    assert_eq!(test.head_file("old.txt"), None);
+    assert_eq!(test.head_file("new.txt").unwrap(), numbered("a", "b"));
+    assert!(test.git(&["status", "--porcelain"]).is_empty());
+}
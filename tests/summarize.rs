@@ -0,0 +1,61 @@
+use rusty_commit::api::summarize::format_reduce_prompt;
+use rusty_commit::config::MessageStyle;
+use rusty_commit::utils::budget::DiffBudget;
+use rusty_commit::utils::diff::{DiffSummary, FileDiff, FileStatus};
+
+fn modified(path: &str) -> FileDiff {
+    FileDiff {
+        path: path.to_string(),
+        old_path: None,
+        status: FileStatus::Modified,
+        old_mode: 0o100644,
+        new_mode: 0o100644,
+        binary: false,
+        old_size: 0,
+        new_size: 0,
+        old_id: None,
+        new_id: None,
+        excluded: None,
+        additions: 1,
+        deletions: 1,
+        hunks: Vec::new(),
+    }
+}
+
+/// `parts` chunks of `files_per_part` files each, with every file listed
+fn chunks(parts: usize, files_per_part: usize) -> (Vec<String>, Vec<DiffSummary>) {
+    let chunks: Vec<DiffSummary> = (0..parts)
+        .map(|p| DiffSummary::from_files((0..files_per_part).map(|f| modified(&format!("src/part{}/file{}.rs", p, f))).collect()))
+        .collect();
+    let files = chunks.iter().flat_map(|chunk| chunk.paths()).collect();
+    (files, chunks)
+}
+
+fn budget(max_tokens: usize) -> DiffBudget {
+    let mut budget = DiffBudget::for_model("openai/gpt-4o", 500, 0);
+    budget.max_tokens = max_tokens;
+    budget
+}
+
+#[test]
+fn reduce_prompt_shows_everything_when_it_fits() {
+    let (files, chunks) = chunks(2, 2);
+    let summaries = vec!["- adds the parser".to_string(), "- wires it up".to_string()];
+    let prompt = format_reduce_prompt(&files, &chunks, &summaries, "", MessageStyle::Conventional, &budget(10_000));
+
+    assert!(prompt.contains("Files changed: src/part0/file0.rs, src/part0/file1.rs"));
+    assert!(prompt.contains("Part 2 (src/part1/file0.rs, src/part1/file1.rs):\n- wires it up"));
+}
+
+#[test]
+fn reduce_prompt_stays_within_the_budget() {
+    let (files, chunks) = chunks(40, 20);
+    let summary: String = (0..30).map(|n| format!("- changes function number {} in several places\n", n)).collect();
+    let summaries = vec![summary; chunks.len()];
+    let budget = budget(8_000);
+
+    let prompt = format_reduce_prompt(&files, &chunks, &summaries, "", MessageStyle::Conventional, &budget);
+    assert!(budget.estimate_tokens(&prompt) <= budget.max_tokens, "{} tokens", budget.estimate_tokens(&prompt));
+    assert!(prompt.contains("Files changed: 800 files"));
+    assert!(prompt.contains("Part 40 (20 files):\n- changes function number 0"));
+}
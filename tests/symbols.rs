@@ -0,0 +1,114 @@
+mod common;
+
+use common::TestRepo;
+use rusty_commit::utils::git::get_git_changes;
+use rusty_commit::utils::symbols::{summarize_symbols, symbols_prompt_text, ChangeKind, SymbolChange};
+
+/// Commits `before` at `path`, stages `after` and describes the symbol changes
+fn changes(path: &str, before: &str, after: &str) -> Vec<String> {
+    let test = TestRepo::new();
+    if !before.is_empty() {
+        test.write(path, before);
+        test.stage(&[path]);
+        test.commit("before");
+    }
+    test.write(path, after);
+    test.stage(&[path]);
+
+    let diff = get_git_changes(&test.repo).unwrap().staged_diff;
+    summarize_symbols(&test.repo, &diff).iter().map(ToString::to_string).collect()
+}
+
+#[test]
+fn rust_rename_is_recognized() {
+    let before = "pub fn parse_config(path: &str) -> Config {\n    read(path)\n}\n";
+    let after = "pub fn load_config(path: &str) -> Config {\n    read(path)\n}\n";
+    assert_eq!(changes("src/config.rs", before, after), ["renamed fn parse_config to load_config"]);
+}
+
+#[test]
+fn rust_rename_needs_an_unchanged_body() {
+    let before = "fn parse(input: &str) -> u8 {\n    0\n}\n";
+    let after = "fn decode(input: &str) -> u8 {\n    1\n}\n";
+    assert_eq!(changes("src/lib.rs", before, after), ["added fn decode", "removed fn parse"]);
+}
+
+#[test]
+fn rust_generic_impl_scopes_methods_to_the_type() {
+    let before = "struct Stack<T>(Vec<T>);\n\nimpl<T: Clone> Stack<T> {\n    fn push(&mut self, item: T) {\n        self.0.push(item);\n    }\n}\n";
+    let after = "struct Stack<T>(Vec<T>);\n\nimpl<T: Clone> Stack<T> {\n    fn push(&mut self, item: T) {\n        self.0.insert(0, item);\n    }\n\n    fn peek(&self) -> Option<&T> {\n        self.0.first()\n    }\n}\n\nenum Order {\n    Lifo,\n}\n";
+    assert_eq!(
+        changes("src/stack.rs", before, after),
+        ["modified fn Stack::push", "added fn Stack::peek", "added enum Order"]
+    );
+}
+
+#[test]
+fn rust_trait_members_explain_the_trait_change() {
+    let before = "trait Store {\n    fn get(&self) -> u8;\n}\n";
+    let after = "trait Store {\n    fn get(&self) -> u16;\n}\n";
+    assert_eq!(changes("src/store.rs", before, after), ["modified fn Store::get"]);
+}
+
+#[test]
+fn python_methods_are_scoped_to_their_class() {
+    let before = "class Cache:\n    def get(self, key):\n        return self.data[key]\n";
+    let after = "class Cache:\n    def get(self, key):\n        return self.data.get(key)\n\n    def clear(self):\n        self.data = {}\n\ndef helper():\n    pass\n";
+    assert_eq!(
+        changes("cache.py", before, after),
+        ["modified def Cache.get", "added def Cache.clear", "added def helper"]
+    );
+}
+
+#[test]
+fn typescript_arrow_functions_and_interfaces() {
+    let after = "export interface User {\n  name: string;\n}\n\nexport const greet = (user: User) => `hi ${user.name}`;\n\nconst limit = 3;\n";
+    assert_eq!(changes("src/user.ts", "", after), ["added interface User", "added function greet"]);
+}
+
+#[test]
+fn go_methods_are_named_after_their_receiver() {
+    let before = "package server\n\ntype Server struct{}\n\nfunc (s *Server) Start() error {\n\treturn nil\n}\n";
+    let after = "package server\n\ntype Server struct{ port int }\n\nfunc (s *Server) Begin() error {\n\treturn nil\n}\n\ntype Handler interface{}\n";
+    assert_eq!(
+        changes("server.go", before, after),
+        ["modified struct Server", "renamed func Server.Start to Server.Begin", "added interface Handler"]
+    );
+}
+
+#[test]
+fn unsupported_and_deleted_files() {
+    assert!(changes("notes.txt", "fn a() {}\n", "fn b() {}\n").is_empty());
+
+    let test = TestRepo::new();
+    test.write("src/gone.rs", "fn gone() {}\n");
+    test.stage_all();
+    test.commit("add");
+    test.remove("src/gone.rs");
+    test.stage_all();
+    let diff = get_git_changes(&test.repo).unwrap().staged_diff;
+    let described: Vec<String> = summarize_symbols(&test.repo, &diff).iter().map(ToString::to_string).collect();
+    assert_eq!(described, ["removed fn gone"]);
+}
+
+#[test]
+fn prompt_text_groups_by_file_and_is_capped() {
+    let change = |path: &str, name: &str| SymbolChange {
+        path: path.to_string(),
+        kind: "fn",
+        name: name.to_string(),
+        change: ChangeKind::Added,
+    };
+
+    let text = symbols_prompt_text(&[change("a.rs", "one"), change("b.rs", "two"), change("a.rs", "three")]);
+    assert_eq!(text, "a.rs: added fn one, added fn three\nb.rs: added fn two");
+
+    let many: Vec<SymbolChange> = (0..40)
+        .flat_map(|f| (0..15).map(move |s| change(&format!("src/f{}.rs", f), &format!("s{}", s))))
+        .collect();
+    let text = symbols_prompt_text(&many);
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 31);
+    assert!(lines[0].ends_with("added fn s9 and 5 more"), "{}", lines[0]);
+    assert_eq!(lines[30], "... and 10 more files");
+}
@@ -0,0 +1,58 @@
+use chrono::{Duration, Utc};
+use rusty_commit::api::usage::{budget_status, totals_by, Usage, UsageRecord};
+use rusty_commit::config::BudgetConfig;
+
+fn record(model: &str, repo: Option<&str>, days_ago: i64, cost: Option<f64>) -> UsageRecord {
+    UsageRecord {
+        timestamp: Utc::now() - Duration::days(days_ago),
+        provider: "openrouter".to_string(),
+        model: model.to_string(),
+        repo: repo.map(str::to_string),
+        usage: Usage { prompt_tokens: 1000, completion_tokens: 50, cost },
+    }
+}
+
+fn limit(monthly_limit: Option<f64>) -> BudgetConfig {
+    BudgetConfig { monthly_limit, ..Default::default() }
+}
+
+#[test]
+fn totals_are_grouped_by_key() {
+    let records = [
+        record("openai/gpt-4o", Some("/src/a"), 0, Some(0.25)),
+        record("openai/gpt-4o", Some("/src/b"), 0, None),
+        record("anthropic/claude-3.5-haiku", Some("/src/a"), 0, Some(0.5)),
+    ];
+
+    let by_model = totals_by(&records, |r| r.model.clone());
+    assert_eq!(by_model.keys().collect::<Vec<_>>(), ["anthropic/claude-3.5-haiku", "openai/gpt-4o"]);
+    let gpt = by_model["openai/gpt-4o"];
+    assert_eq!((gpt.requests, gpt.prompt_tokens, gpt.completion_tokens), (2, 2000, 100));
+    assert_eq!(gpt.cost, 0.25);
+
+    let by_repo = totals_by(&records, |r| r.repo.clone());
+    assert_eq!(by_repo[&Some("/src/a".to_string())].requests, 2);
+    assert_eq!(by_repo[&Some("/src/a".to_string())].cost, 0.75);
+
+    assert!(totals_by(&[], |r| r.model.clone()).is_empty());
+}
+
+#[test]
+fn budget_is_only_reached_by_this_months_spending() {
+    let records = [
+        record("openai/gpt-4o", None, 0, Some(3.0)),
+        record("openai/gpt-4o", None, 0, None),
+        record("openai/gpt-4o", None, 62, Some(100.0)),
+    ];
+
+    assert_eq!(budget_status(&limit(Some(5.0)), &records), None);
+    assert_eq!(budget_status(&limit(Some(3.0)), &records), Some((3.0, 3.0)));
+    assert_eq!(budget_status(&limit(Some(1.0)), &records), Some((3.0, 1.0)));
+}
+
+#[test]
+fn no_limit_means_no_budget() {
+    let records = [record("openai/gpt-4o", None, 0, Some(1000.0))];
+    assert_eq!(budget_status(&limit(None), &records), None);
+    assert_eq!(budget_status(&limit(Some(1.0)), &[]), None);
+}